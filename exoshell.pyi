@@ -1,4 +1,5 @@
-from collections.abc import Sequence
+from collections.abc import Callable, Sequence
+from typing import Literal
 
 class Action:
     class Writeline:
@@ -11,14 +12,94 @@ class Action:
         _0: str
         def __init__(self, _0: str) -> None: ...
 
+    class WriteBytes:
+        __match_args__ = ("_0",)
+        _0: bytes
+        def __init__(self, _0: bytes) -> None: ...
+
     class Quit:
         __match_args__ = ()
 
+class Printer:
+    def print(self, value: str) -> None:
+        """Queue output, shown by the console on its next update. Safe from any thread."""
+    def print_bytes(self, value: bytes) -> None:
+        """Like print, for bytes that may not be valid UTF-8."""
+
 class Console:
-    def __init__(self, name: str, titles: Sequence[str]) -> None: ...
+    def __init__(
+        self,
+        name: str,
+        titles: Sequence[str],
+        terminator: Literal["lf", "cr", "crlf", "none"] | None = None,
+    ) -> None:
+        """terminator overrides the one from config.ron, which defaults to "lf"."""
+    @property
+    def terminator(self) -> str:
+        """Line ending to append to Writeline lines. Raw mode sends it for Enter."""
     def start(self) -> None: ...
     def stop(self) -> None: ...
     def update(
         self, timeout: int
-    ) -> Action.Writeline | Action.Write | Action.Quit | None: ...
+    ) -> Action.Writeline | Action.Write | Action.WriteBytes | Action.Quit | None: ...
+    async def next_action(self) -> Action.Writeline | Action.Write | Action.WriteBytes | Action.Quit:
+        """Wait for the next action without blocking the event loop."""
+    def set_completer(
+        self, completer: Callable[[str, int], Sequence[str]] | None
+    ) -> None:
+        """Called with (line, cursor) on Tab. Candidates replace the word before the cursor."""
+    def set_validator(self, validator: Callable[[str], bool] | None) -> None:
+        """Called with the input on Enter. Incomplete input gets a new row instead."""
+    def add_mode(
+        self,
+        name: str,
+        key: str,
+        handler: Callable[
+            [str], Action.Writeline | Action.Write | Action.WriteBytes | Action.Quit | str | None
+        ],
+        color: str = "blue",
+        keybinds: Sequence[str] = (),
+    ) -> None:
+        """Register a mode selected by key from Prefix mode.
+
+        handler is called with key names like "a", "ctrl+a" or "enter", and returns
+        an Action, the name of a mode to switch to ("line", "raw", "prefix", "hex"
+        or a custom mode), or None.
+
+        Handlers, completers and validators run while the console is busy. print and
+        print_bytes still work, shown once the callback returns, but other Console
+        methods raise RuntimeError.
+        """
+    def set_output_policy(
+        self,
+        returns: Literal["newline", "keep", "strip"] | None = None,
+        backspaces: Literal["interpret", "strip"] | None = None,
+        escapes: Literal["pass", "color", "strip"] | None = None,
+        controls: Literal["caret", "hex"] | None = None,
+        invalid: Literal["hex", "replace"] | None = None,
+    ) -> None:
+        """Change how printed output is cleaned up. Arguments left as None are unchanged.
+
+        returns: what a bare carriage return does.
+        escapes: "color" keeps only colors and styles, so output can't move the cursor.
+        controls: other control characters are shown as "^G" or "<0x07>".
+        invalid: bytes that aren't UTF-8 are shown as "<0xff>" or "\ufffd".
+        """
+    dump: bool
+    """Show printed output as offset, hex and ASCII columns. Toggled with d in Prefix mode."""
+    echo: bool
+    """Show what Raw mode sends, for devices that don't echo. Line and Hex mode are never
+    echoed. Toggled with e in Prefix mode."""
+    def set_key_encoding(
+        self, encoding: Literal["vt100", "xterm", "application"]
+    ) -> None:
+        """Escape sequences Raw mode sends for keys like arrows, Home or F1.
+
+        "application" sends application cursor keys, e.g. ESC O A for up.
+        """
+    def set_edit_mode(self, mode: Literal["emacs", "vi"]) -> None: ...
+    def set_word_style(self, style: Literal["whitespace", "punctuation"]) -> None: ...
+    def printer(self) -> Printer: ...
     def print(self, value: str) -> None: ...
+    def print_bytes(self, value: bytes) -> None:
+        """Print bytes from a device. UTF-8 split across calls is kept whole."""
@@ -0,0 +1,244 @@
+use exoshell::{KeyEncoding, Terminator};
+use std::path::Path;
+
+use crate::serial::Settings;
+
+pub const USAGE: &str = "\
+usage: exoshell [OPTIONS] -- COMMAND [ARGS...]
+       exoshell [OPTIONS] --connect HOST:PORT
+       exoshell [OPTIONS] --listen ADDRESS:PORT
+       exoshell [OPTIONS] --serial DEVICE
+
+Runs COMMAND in a pseudo-terminal, or talks to a TCP peer or serial device,
+behind the exoshell console.
+
+options:
+  -c, --connect HOST:PORT     connect to a TCP server
+  -l, --listen ADDRESS:PORT   wait for one TCP connection
+  -s, --serial DEVICE         open a tty device, e.g. /dev/ttyUSB0
+  -b, --baud RATE             serial baud rate, defaults to 115200
+      --parity PARITY         serial parity: none, even or odd, defaults to none
+      --stop-bits BITS        serial stop bits: 1 or 2, defaults to 1
+      --flow FLOW             serial flow control: none, hardware or software, defaults to none
+  -t, --terminator ENDING     sent after each line: lf, cr, crlf or none, defaults to config.ron
+  -e, --echo                  show what raw mode sends, for devices that don't echo
+  -k, --keys ENCODING         keys sent in raw mode: vt100, xterm or application, defaults to config.ron
+  -n, --name NAME             history file name, defaults to the command name or address
+      --term TERM             TERM for the command, defaults to dumb
+  -h, --help                  show this message";
+
+// What the console is connected to
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Command(Vec<String>),
+    Connect(String),
+    Listen(String),
+    Serial(String),
+}
+
+impl Target {
+    // e.g. "python" for /usr/bin/python
+    fn name(&self) -> String {
+        match self {
+            Self::Command(command) => command
+                .first()
+                .and_then(|program| Path::new(program).file_name())
+                .map_or("exoshell".to_string(), |name| {
+                    name.to_string_lossy().into_owned()
+                }),
+            Self::Connect(address) | Self::Listen(address) => address.to_string(),
+            Self::Serial(path) => Path::new(path)
+                .file_name()
+                .map_or(path.to_string(), |name| name.to_string_lossy().into_owned()),
+        }
+    }
+
+    // Shown in the console header
+    pub fn title(&self, serial: &Settings) -> String {
+        match self {
+            Self::Command(command) => command.join(" "),
+            Self::Connect(address) => format!("tcp://{}", address),
+            Self::Listen(address) => format!("listen://{}", address),
+            Self::Serial(path) => format!("{} {}", path, serial),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    pub name: String,
+    pub term: String,
+    pub terminator: Option<Terminator>,
+    pub keys: Option<KeyEncoding>,
+    pub echo: bool,
+    pub serial: Settings,
+    pub target: Target,
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut name = None;
+        let mut term = "dumb".to_string();
+        let mut terminator = None;
+        let mut keys = None;
+        let mut echo = false;
+        let mut serial = Settings::default();
+        let mut targets = Vec::new();
+        let mut help = false;
+
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{} needs a value", option))
+            };
+
+            match arg.as_str() {
+                "-c" | "--connect" => targets.push(Target::Connect(value(&arg)?)),
+                "-l" | "--listen" => targets.push(Target::Listen(value(&arg)?)),
+                "-s" | "--serial" => targets.push(Target::Serial(value(&arg)?)),
+                "-b" | "--baud" => {
+                    serial.baud = value(&arg)?
+                        .parse()
+                        .map_err(|_| format!("{} needs a number", arg))?
+                }
+                "--parity" => serial.parity = value(&arg)?.parse()?,
+                "--stop-bits" => serial.stop_bits = value(&arg)?.parse()?,
+                "--flow" => serial.flow = value(&arg)?.parse()?,
+                "-t" | "--terminator" => {
+                    terminator = Some(value(&arg)?.parse().map_err(|err| format!("{}", err))?)
+                }
+                "-e" | "--echo" => echo = true,
+                "-k" | "--keys" => {
+                    keys = Some(value(&arg)?.parse().map_err(|err| format!("{}", err))?)
+                }
+                "-n" | "--name" => name = Some(value(&arg)?),
+                "--term" => term = value(&arg)?,
+                "-h" | "--help" => help = true,
+                "--" => {
+                    targets.push(Target::Command(args.collect()));
+                    break;
+                }
+                option if option.starts_with('-') => {
+                    return Err(format!("unknown option {}", option))
+                }
+                _ => {
+                    targets.push(Target::Command([arg].into_iter().chain(args).collect()));
+                    break;
+                }
+            }
+        }
+
+        let target = match targets.pop() {
+            _ if help => Target::Command(Vec::new()),
+            Some(Target::Command(command)) if command.is_empty() => {
+                return Err("no command given".to_string())
+            }
+            Some(target) if targets.is_empty() => target,
+            Some(_) => return Err("only one command or address can be given".to_string()),
+            None => return Err("no command or address given".to_string()),
+        };
+
+        Ok(Self {
+            name: name.unwrap_or_else(|| target.name()),
+            term,
+            terminator,
+            keys,
+            echo,
+            serial,
+            target,
+            help,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Args, Target};
+    use crate::serial::{Flow, Parity, Settings, StopBits};
+    use exoshell::{KeyEncoding, Terminator};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn command_after_separator() {
+        let args = parse(&["--", "/usr/bin/python", "-i"]).unwrap();
+        assert_eq!(
+            Target::Command(vec!["/usr/bin/python".into(), "-i".into()]),
+            args.target
+        );
+        assert_eq!("python", args.name);
+        assert_eq!("dumb", args.term);
+    }
+
+    #[test]
+    fn options_before_command() {
+        let args = parse(&["-n", "debug", "--term", "xterm", "gdb", "--quiet"]).unwrap();
+        assert_eq!(
+            Target::Command(vec!["gdb".into(), "--quiet".into()]),
+            args.target
+        );
+        assert_eq!("debug", args.name);
+        assert_eq!("xterm", args.term);
+    }
+
+    #[test]
+    fn tcp_targets() {
+        let args = parse(&["--connect", "lab:2300", "-t", "crlf", "-e"]).unwrap();
+        assert_eq!(Target::Connect("lab:2300".into()), args.target);
+        assert_eq!("lab:2300", args.name);
+        assert_eq!(Some(Terminator::CrLf), args.terminator);
+        assert!(args.echo);
+
+        let args = parse(&["-l", "127.0.0.1:0", "--keys", "application"]).unwrap();
+        assert_eq!(Target::Listen("127.0.0.1:0".into()), args.target);
+        assert_eq!(Some(KeyEncoding::Application), args.keys);
+    }
+
+    #[test]
+    fn serial_target() {
+        let args = parse(&[
+            "-s",
+            "/dev/ttyUSB0",
+            "-b",
+            "9600",
+            "--parity",
+            "even",
+            "--stop-bits",
+            "2",
+            "--flow",
+            "hardware",
+        ])
+        .unwrap();
+
+        assert_eq!(Target::Serial("/dev/ttyUSB0".into()), args.target);
+        assert_eq!("ttyUSB0", args.name);
+        assert_eq!(
+            Settings {
+                baud: 9600,
+                parity: Parity::Even,
+                stop_bits: StopBits::Two,
+                flow: Flow::Hardware,
+            },
+            args.serial
+        );
+        assert_eq!("9600 8E2 rtscts", args.serial.to_string());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--name"]).is_err());
+        assert!(parse(&["--bogus", "--", "sh"]).is_err());
+        assert!(parse(&["--", "sh"]).is_ok());
+        assert!(parse(&["--"]).is_err());
+        assert!(parse(&["-c", "lab:1", "--", "sh"]).is_err());
+        assert!(parse(&["-t", "lfcr", "--", "sh"]).is_err());
+        assert!(parse(&["-k", "vt52", "--", "sh"]).is_err());
+        assert!(parse(&["-s", "/dev/ttyS0", "--parity", "mark"]).is_err());
+        assert!(parse(&["-s", "/dev/ttyS0", "-b", "fast"]).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use exoshell::{Action, Console, Printer, Result};
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How often to check whether the remote end has closed
+const CLOSE_INTERVAL: Duration = Duration::from_millis(50);
+
+// Print everything read from the remote end on a background thread, until it closes
+pub fn spawn_reader(mut reader: impl Read + Send + 'static, printer: Printer) -> Arc<AtomicBool> {
+    let closed = Arc::new(AtomicBool::new(false));
+
+    {
+        let closed = closed.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(len) => printer.print_bytes(&buffer[..len]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        // e.g. EIO from a pty once the child exits
+                        log::debug!("read failed: {}", err);
+                        break;
+                    }
+                }
+            }
+
+            closed.store(true, Ordering::Relaxed);
+        });
+    }
+
+    closed
+}
+
+// Send console actions to the remote end, until either side quits
+pub fn run(
+    console: &mut Console,
+    writer: &mut impl Write,
+    closed: &AtomicBool,
+    mut resize: impl FnMut(u16, u16) -> io::Result<()>,
+) -> Result<()> {
+    let mut size = console.size();
+
+    while !closed.load(Ordering::Relaxed) {
+        let action = console.update(CLOSE_INTERVAL)?;
+
+        // Not worth ending the session over, e.g. once the child has exited
+        if console.size() != size {
+            size = console.size();
+            if let Err(err) = resize(size.0, size.1) {
+                log::debug!("resize failed: {}", err);
+            }
+        }
+
+        match action {
+            // One write, so the line and terminator share a packet
+            Some(Action::Writeline(line)) => {
+                let line = format!("{}{}", line, console.terminator().as_str());
+                writer.write_all(line.as_bytes())?
+            }
+            Some(Action::Write(value)) => writer.write_all(value.as_bytes())?,
+            Some(Action::WriteBytes(bytes)) => writer.write_all(&bytes)?,
+            Some(Action::Quit()) => return Ok(()),
+            None => continue,
+        }
+        writer.flush()?;
+    }
+
+    // Show the last output before the remote end closed
+    console.update(Duration::ZERO)?;
+    Ok(())
+}
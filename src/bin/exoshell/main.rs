@@ -0,0 +1,97 @@
+mod args;
+mod bridge;
+mod pty;
+mod serial;
+mod tcp;
+
+use args::{Args, Target, USAGE};
+use exoshell::{Console, Result};
+use pty::Pty;
+use std::env;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+// Turn a libc return value into an io::Result
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    match result {
+        -1 => Err(io::Error::last_os_error()),
+        result => Ok(result),
+    }
+}
+
+fn run(args: &Args) -> Result<()> {
+    match &args.target {
+        Target::Command(command) => {
+            let pty = Pty::spawn(command, &args.term)?;
+            let resize = |cols, rows| pty.resize(cols, rows);
+            attach(args, pty.reader()?, pty.writer()?, resize)
+        }
+        Target::Connect(address) => {
+            let stream = tcp::connect(address)?;
+            attach(args, stream.try_clone()?, stream, |_, _| Ok(()))
+        }
+        Target::Listen(address) => {
+            let stream = tcp::listen(address)?;
+            attach(args, stream.try_clone()?, stream, |_, _| Ok(()))
+        }
+        Target::Serial(path) => {
+            let device = serial::open(path, &args.serial)?;
+            attach(args, device.try_clone()?, device, |_, _| Ok(()))
+        }
+    }
+}
+
+// Run the console until either it or the other end quits. resize is called with the
+// terminal's columns and rows when they change.
+fn attach(
+    args: &Args,
+    reader: impl Read + Send + 'static,
+    mut writer: impl Write,
+    resize: impl FnMut(u16, u16) -> io::Result<()>,
+) -> Result<()> {
+    let mut console = Console::new(
+        &args.name,
+        &[args.name.clone(), args.target.title(&args.serial)],
+    )?;
+    if let Some(terminator) = args.terminator {
+        console.set_terminator(terminator);
+    }
+    if let Some(keys) = args.keys {
+        console.set_key_encoding(keys);
+    }
+    if args.echo {
+        console.set_echo(true);
+    }
+    let closed = bridge::spawn_reader(reader, console.printer());
+
+    console.start()?;
+    let result = bridge::run(&mut console, &mut writer, &closed, resize);
+    console.stop()?;
+
+    result
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("exoshell: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if args.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("exoshell: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
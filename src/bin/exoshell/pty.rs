@@ -0,0 +1,109 @@
+use crossterm::terminal;
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+
+use crate::check;
+
+// A child process attached to the slave side of a pseudo-terminal
+pub struct Pty {
+    master: File,
+    child: Child,
+}
+
+impl Pty {
+    pub fn spawn(command: &[String], term: &str) -> io::Result<Self> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command given"))?;
+
+        let master = unsafe {
+            let fd = check(libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY))?;
+            File::from_raw_fd(fd)
+        };
+        let fd = master.as_raw_fd();
+
+        let path = unsafe {
+            check(libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC))?;
+            check(libc::grantpt(fd))?;
+            check(libc::unlockpt(fd))?;
+
+            // Not reentrant, but nothing else opens ptys concurrently
+            let name = libc::ptsname(fd);
+            if name.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            CStr::from_ptr(name).to_string_lossy().into_owned()
+        };
+
+        // Match the terminal, so the child wraps output where we do
+        let (cols, rows) = terminal::size()?;
+        set_size(&master, cols, rows)?;
+
+        let slave = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)?;
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .env("TERM", term)
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave);
+
+        // Make the pty the child's controlling terminal, so ^C and job control work
+        unsafe {
+            command.pre_exec(|| {
+                check(libc::setsid())?;
+                check(libc::ioctl(0, libc::TIOCSCTTY, 0))?;
+                Ok(())
+            });
+        }
+
+        let child = command.spawn()?;
+        Ok(Self { master, child })
+    }
+
+    // Output from the child, failing once it has exited
+    pub fn reader(&self) -> io::Result<File> {
+        self.master.try_clone()
+    }
+
+    // Input to the child
+    pub fn writer(&self) -> io::Result<File> {
+        self.master.try_clone()
+    }
+
+    // The child gets SIGWINCH, so full-screen programs redraw at the new size
+    pub fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        set_size(&self.master, cols, rows)
+    }
+}
+
+fn set_size(master: &File, cols: u16, rows: u16) -> io::Result<()> {
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    unsafe { check(libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size))? };
+    Ok(())
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        // The child may outlive us otherwise, e.g. a REPL ignoring the hangup
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::str::FromStr;
+
+use crate::check;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+impl FromStr for Parity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "none" => Ok(Self::None),
+            "even" => Ok(Self::Even),
+            "odd" => Ok(Self::Odd),
+            _ => Err(format!(
+                "unknown parity {:?}, expected none, even or odd",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StopBits {
+    #[default]
+    One,
+    Two,
+}
+
+impl FromStr for StopBits {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "1" => Ok(Self::One),
+            "2" => Ok(Self::Two),
+            _ => Err(format!("unknown stop bits {:?}, expected 1 or 2", value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Flow {
+    #[default]
+    None,
+    // RTS/CTS
+    Hardware,
+    // XON/XOFF
+    Software,
+}
+
+impl FromStr for Flow {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "none" => Ok(Self::None),
+            "hardware" => Ok(Self::Hardware),
+            "software" => Ok(Self::Software),
+            _ => Err(format!(
+                "unknown flow control {:?}, expected none, hardware or software",
+                value
+            )),
+        }
+    }
+}
+
+// Line settings, always 8 data bits
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    pub baud: u32,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow: Flow,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            baud: 115200,
+            parity: Parity::default(),
+            stop_bits: StopBits::default(),
+            flow: Flow::default(),
+        }
+    }
+}
+
+// e.g. "115200 8N1", or "115200 8E2 rtscts"
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Even => 'E',
+            Parity::Odd => 'O',
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        write!(f, "{} 8{}{}", self.baud, parity, stop_bits)?;
+
+        match self.flow {
+            Flow::None => Ok(()),
+            Flow::Hardware => write!(f, " rtscts"),
+            Flow::Software => write!(f, " xonxoff"),
+        }
+    }
+}
+
+fn speed(baud: u32) -> io::Result<libc::speed_t> {
+    let speed = match baud {
+        1200 => libc::B1200,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        #[cfg(target_os = "linux")]
+        460800 => libc::B460800,
+        #[cfg(target_os = "linux")]
+        921600 => libc::B921600,
+        #[cfg(target_os = "linux")]
+        1000000 => libc::B1000000,
+        #[cfg(target_os = "linux")]
+        1500000 => libc::B1500000,
+        #[cfg(target_os = "linux")]
+        2000000 => libc::B2000000,
+        #[cfg(target_os = "linux")]
+        3000000 => libc::B3000000,
+        #[cfg(target_os = "linux")]
+        4000000 => libc::B4000000,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported baud rate {}", baud),
+            ))
+        }
+    };
+    Ok(speed)
+}
+
+// Open a tty device in raw mode, e.g. /dev/ttyUSB0
+pub fn open(path: &str, settings: &Settings) -> io::Result<File> {
+    // Non-blocking, so opening doesn't wait for carrier detect
+    let device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY | libc::O_NONBLOCK)
+        .open(path)?;
+    let fd = device.as_raw_fd();
+
+    unsafe {
+        let mut termios = MaybeUninit::<libc::termios>::uninit();
+        check(libc::tcgetattr(fd, termios.as_mut_ptr()))?;
+        let mut termios = termios.assume_init();
+
+        libc::cfmakeraw(&mut termios);
+        check(libc::cfsetspeed(&mut termios, speed(settings.baud)?))?;
+
+        termios.c_cflag |= libc::CREAD | libc::CLOCAL;
+        termios.c_cflag &= !(libc::PARENB | libc::PARODD | libc::CSTOPB | libc::CRTSCTS);
+        termios.c_iflag &= !(libc::IXON | libc::IXOFF | libc::IXANY);
+
+        match settings.parity {
+            Parity::None => {}
+            Parity::Even => termios.c_cflag |= libc::PARENB,
+            Parity::Odd => termios.c_cflag |= libc::PARENB | libc::PARODD,
+        }
+        if settings.stop_bits == StopBits::Two {
+            termios.c_cflag |= libc::CSTOPB;
+        }
+        match settings.flow {
+            Flow::None => {}
+            Flow::Hardware => termios.c_cflag |= libc::CRTSCTS,
+            Flow::Software => termios.c_iflag |= libc::IXON | libc::IXOFF,
+        }
+
+        // Reads block until at least one byte arrives
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+
+        check(libc::tcsetattr(fd, libc::TCSANOW, &termios))?;
+
+        let flags = check(libc::fcntl(fd, libc::F_GETFL))?;
+        check(libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK))?;
+    }
+
+    Ok(device)
+}
@@ -0,0 +1,21 @@
+use std::io;
+use std::net::{TcpListener, TcpStream};
+
+pub fn connect(address: &str) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect(address)?;
+    // Keystrokes in Raw mode should go out immediately
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+// Wait for a single connection, then stop listening
+pub fn listen(address: &str) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("exoshell: listening on {}", listener.local_addr()?);
+
+    let (stream, peer) = listener.accept()?;
+    eprintln!("exoshell: connection from {}", peer);
+
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
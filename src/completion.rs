@@ -0,0 +1,117 @@
+// Supplies candidates for the word before the cursor
+pub trait Completer: Send + Sync {
+    fn complete(&self, line: &str, cursor: usize) -> Vec<String>;
+}
+
+// Candidates shown below the line, cycled with Tab/Shift+Tab
+#[derive(Debug)]
+pub struct Menu {
+    candidates: Vec<String>,
+    selected: Option<usize>,
+    // Character index where the completed word starts
+    start: usize,
+}
+
+impl Menu {
+    pub fn new(candidates: Vec<String>, start: usize) -> Self {
+        Self {
+            candidates,
+            selected: None,
+            start,
+        }
+    }
+
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn next(&mut self) -> &str {
+        let index = match self.selected {
+            Some(index) => (index + 1) % self.candidates.len(),
+            None => 0,
+        };
+        self.selected = Some(index);
+        &self.candidates[index]
+    }
+
+    pub fn prev(&mut self) -> &str {
+        let index = match self.selected {
+            Some(0) | None => self.candidates.len() - 1,
+            Some(index) => index - 1,
+        };
+        self.selected = Some(index);
+        &self.candidates[index]
+    }
+}
+
+// Character index where the whitespace-delimited word ending at cursor starts
+pub fn word_start(line: &str, cursor: usize) -> usize {
+    line.chars()
+        .take(cursor)
+        .collect::<Vec<_>>()
+        .iter()
+        .rposition(|c| c.is_whitespace())
+        .map_or(0, |index| index + 1)
+}
+
+pub fn common_prefix(candidates: &[String]) -> String {
+    let mut candidates = candidates.iter();
+
+    let Some(first) = candidates.next() else {
+        return String::new();
+    };
+
+    let mut prefix: &str = first;
+    for candidate in candidates {
+        let len = prefix
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((index, c), _)| index + c.len_utf8());
+        prefix = &prefix[..len];
+    }
+
+    prefix.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{common_prefix, word_start, Menu};
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn common_prefix_of_candidates() {
+        assert_eq!("reg", common_prefix(&strings(&["read", "reg"])[1..]));
+        assert_eq!("re", common_prefix(&strings(&["read", "reg", "reset"])));
+        assert_eq!("", common_prefix(&strings(&["dev", "reg"])));
+        assert_eq!("", common_prefix(&[]));
+    }
+
+    #[test]
+    fn word_start_at_cursor() {
+        assert_eq!(0, word_start("reg", 3));
+        assert_eq!(4, word_start("reg re", 6));
+        assert_eq!(4, word_start("reg ", 4));
+    }
+
+    #[test]
+    fn menu_cycles() {
+        let mut menu = Menu::new(strings(&["a", "b"]), 0);
+        assert_eq!("b", menu.prev());
+        assert_eq!("a", menu.next());
+        assert_eq!("b", menu.next());
+        assert_eq!("a", menu.next());
+    }
+}
@@ -0,0 +1,55 @@
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::encoder::KeyEncoding;
+use crate::output::Policy;
+use crate::path;
+use crate::terminator::Terminator;
+use crate::{Error, Result};
+
+// Console settings from config.ron, e.g. `(terminator: crlf, output: (escapes: strip))`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub terminator: Terminator,
+    pub output: Policy,
+    pub keys: KeyEncoding,
+    // Local echo in Raw mode
+    pub echo: bool,
+    // Show output as a hex dump
+    pub dump: bool,
+}
+
+impl Config {
+    // Settings from the data directory, or defaults if there are none
+    pub fn load() -> Result<Self> {
+        let path = path::data_dir()?.join("config.ron");
+
+        match Self::load_from(&path) {
+            Err(Error::Io(io::ErrorKind::NotFound)) => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Ok(ron::from_str(&contents)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+    use crate::terminator::Terminator;
+
+    #[test]
+    fn parse() {
+        let config: Config = ron::from_str("(terminator: crlf)").unwrap();
+        assert_eq!(Terminator::CrLf, config.terminator);
+
+        let config: Config = ron::from_str("()").unwrap();
+        assert_eq!(Config::default(), config);
+    }
+}
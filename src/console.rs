@@ -1,112 +1,357 @@
 use crossterm::{cursor, event, style, terminal, QueueableCommand};
-use pyo3::prelude::*;
+use std::future::Future;
 use std::io::{self, Stdout, Write};
 use std::time::Duration;
 
+use crate::completion::Completer;
+use crate::config::Config;
+use crate::custom::{Custom, Handler};
+use crate::dump::Dump;
+use crate::encoder::KeyEncoding;
 use crate::history::History;
+use crate::inbox::{Inbox, Reader};
+use crate::keymap::Keymap;
 use crate::mode::Modes;
+use crate::output::{Normalizer, Policy};
 use crate::shell::Shell;
+use crate::terminator::Terminator;
+use crate::validator::Validator;
+use crate::vi::EditMode;
+use crate::word::Words;
+use crate::Result;
 
-#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Action {
+    // A submitted line, without the terminator, see Console::terminator
     Writeline(String),
     Write(String),
+    // Arbitrary bytes, e.g. from hex input
+    WriteBytes(Vec<u8>),
     Quit(),
 }
 
-#[pyclass]
+// Cloneable handle for printing from other threads, shown on the console's next update
+#[derive(Clone)]
+pub struct Printer(Inbox);
+
+impl Printer {
+    pub fn print(&self, output: &str) {
+        self.0.push_output(output.as_bytes());
+    }
+
+    pub fn print_bytes(&self, output: &[u8]) {
+        self.0.push_output(output);
+    }
+}
+
+// Waits for console input without borrowing the console, see Console::waiter
+#[derive(Clone)]
+pub struct Waiter(Inbox);
+
+impl Waiter {
+    // Block until there is something to poll, or timeout passes
+    pub fn wait(&self, timeout: Duration) {
+        self.0.wait(timeout);
+    }
+
+    // Resolves once there is something to poll
+    pub fn ready(&self) -> impl Future<Output = ()> {
+        self.0.ready()
+    }
+}
+
+// History from the data directory, created if missing, or kept in memory if that fails
+fn load_history(name: &str) -> History {
+    match History::load_by_name(name) {
+        Ok(history) => {
+            log::debug!("successfully loaded history for {}", name);
+            history
+        }
+        Err(err) => {
+            log::debug!("could not load history for {}: {}", name, err);
+            match History::create(name) {
+                Ok(history) => {
+                    log::debug!("created history file for {}", name);
+                    history
+                }
+                Err(_) => {
+                    log::debug!("using local history");
+                    History::default()
+                }
+            }
+        }
+    }
+}
+
 pub struct Console {
     shell: Shell,
     modes: Modes,
+    inbox: Inbox,
+    reader: Option<Reader>,
+    terminator: Terminator,
+    normalizer: Normalizer,
+    dump: Dump,
     cols: u16,
+    rows: u16,
     last_col: u16,
     stdout: Stdout,
 }
 
-#[pymethods]
 impl Console {
-    #[new]
-    pub fn new(name: String, titles: Vec<String>) -> PyResult<Self> {
-        let (cols, _) = terminal::size()?;
-
-        let history = match History::load_by_name(&name) {
-            Ok(history) => {
-                log::debug!("successfully loaded history for {}", name);
-                history
-            }
-            Err(err) => {
-                log::debug!("could not load history for {}: {}", name, err);
-                match History::create(&name) {
-                    Ok(history) => {
-                        log::debug!("created history file for {}", name);
-                        history
-                    }
-                    Err(_) => {
-                        log::debug!("using local history");
-                        History::default()
-                    }
-                }
-            }
-        };
+    pub fn new(name: &str, titles: &[impl ToString]) -> Result<Self> {
+        let (cols, rows) = terminal::size()?;
+
+        let history = load_history(name);
+        let hex_history = load_history(&format!("{}.hex", name));
+
+        let mut modes = Modes::new(history, hex_history);
+        match Keymap::load() {
+            Ok(keymap) => modes.set_keymap(keymap),
+            Err(err) => log::warn!("could not load keymap, using defaults: {}", err),
+        }
+
+        let config = Config::load().unwrap_or_else(|err| {
+            log::warn!("could not load config, using defaults: {}", err);
+            Config::default()
+        });
+        modes.set_terminator(config.terminator);
+        modes.set_key_encoding(config.keys);
+        modes.set_echo(config.echo);
+        modes.set_dump(config.dump);
 
         let mut shell = Shell::new(cols)?;
 
         for title in titles.iter() {
-            shell.push_title(title)
+            shell.push_title(title.to_string())
         }
         let stdout = io::stdout();
 
         Ok(Self {
             shell,
-            modes: Modes::new(history),
+            modes,
+            inbox: Inbox::default(),
+            reader: None,
+            terminator: config.terminator,
+            normalizer: Normalizer::new(config.output),
+            dump: Dump::default(),
             last_col: 0,
             cols,
+            rows,
             stdout,
         })
     }
 
-    pub fn start(&mut self) -> PyResult<()> {
+    pub fn start(&mut self) -> Result<()> {
         terminal::enable_raw_mode()?;
+        self.listen();
+        self.stdout.queue(event::EnableBracketedPaste)?;
         self.shell.write(&mut self.stdout, &self.modes)?;
         self.stdout.flush()?;
         Ok(())
     }
 
-    pub fn stop(&mut self) -> PyResult<()> {
+    pub fn stop(&mut self) -> Result<()> {
+        // Joins the reader, so nothing reads the terminal after this
+        self.reader = None;
         self.shell.clear(&mut self.stdout)?;
+        self.stdout.queue(event::DisableBracketedPaste)?;
         self.stdout.flush()?;
         terminal::disable_raw_mode()?;
         Ok(())
     }
 
-    pub fn update(&mut self, timeout_ns: u64) -> PyResult<Option<Action>> {
-        if event::poll(Duration::from_nanos(timeout_ns))? {
-            let event = event::read()?;
-            self.shell.clear(&mut self.stdout)?;
+    pub fn update(&mut self, timeout: Duration) -> Result<Option<Action>> {
+        if let Some(action) = self.poll()? {
+            return Ok(Some(action));
+        }
 
-            let message = match event {
-                event::Event::Key(key) => {
-                    let action = self.modes.on_key(key);
-                    action
-                }
-                event::Event::Resize(cols, _) => {
-                    self.cols = cols;
-                    self.shell.resize(cols)?;
-                    None
-                }
-                _ => None,
-            };
+        self.waiter().wait(timeout);
+        self.poll()
+    }
+
+    pub async fn next_action(&mut self) -> Result<Action> {
+        loop {
+            if let Some(action) = self.poll()? {
+                return Ok(action);
+            }
+            self.waiter().ready().await;
+        }
+    }
+
+    // Handle whatever is ready without blocking
+    pub fn poll(&mut self) -> Result<Option<Action>> {
+        // e.g. the remaining lines of a multi-line paste
+        if let Some(action) = self.modes.next_queued() {
+            return Ok(Some(action));
+        }
+
+        self.listen();
+        self.process()
+    }
+
+    // For waiting outside a borrow of the console, then calling poll
+    pub fn waiter(&mut self) -> Waiter {
+        self.listen();
+        Waiter(self.inbox.clone())
+    }
+
+    // Line ending for submitted lines, also sent by Enter in Raw mode
+    pub fn set_terminator(&mut self, terminator: Terminator) {
+        self.terminator = terminator;
+        self.modes.set_terminator(terminator);
+    }
+
+    pub fn terminator(&self) -> Terminator {
+        self.terminator
+    }
+
+    // Escape sequences Raw mode sends for cursor, editing and function keys
+    pub fn set_key_encoding(&mut self, encoding: KeyEncoding) {
+        self.modes.set_key_encoding(encoding);
+    }
+
+    // Show what Raw mode sends, also toggled from Prefix mode. Only Raw mode echoes
+    pub fn set_echo(&mut self, echo: bool) {
+        self.modes.set_echo(echo);
+    }
+
+    pub fn echo(&self) -> bool {
+        self.modes.echo()
+    }
+
+    // Show printed output as offset, hex and ASCII columns, also toggled from Prefix mode
+    pub fn set_dump(&mut self, dump: bool) {
+        self.modes.set_dump(dump);
+    }
+
+    pub fn dump(&self) -> bool {
+        self.modes.dump()
+    }
+
+    // How printed output is cleaned up before it is shown
+    pub fn set_output_policy(&mut self, policy: Policy) {
+        self.normalizer.set_policy(policy);
+    }
+
+    pub fn output_policy(&self) -> Policy {
+        self.normalizer.policy()
+    }
+
+    pub fn set_completer(&mut self, completer: Option<Box<dyn Completer>>) {
+        self.modes.set_completer(completer);
+    }
+
+    pub fn set_validator(&mut self, validator: Option<Box<dyn Validator>>) {
+        self.modes.set_validator(validator);
+    }
 
-            self.shell.write(&mut self.stdout, &self.modes)?;
-            self.stdout.flush()?;
+    pub fn add_mode(
+        &mut self,
+        name: impl ToString,
+        key: char,
+        color: style::Color,
+        keybinds: Vec<String>,
+        handler: Box<dyn Handler>,
+    ) -> Result<()> {
+        self.modes
+            .add_custom(Custom::new(name, key, color, keybinds, handler))
+    }
+
+    pub fn set_edit_mode(&mut self, mode: EditMode) {
+        self.modes.set_edit_mode(mode);
+    }
+
+    pub fn set_word_style(&mut self, words: Words) {
+        self.modes.set_words(words);
+    }
+
+    // Replaces the keymap loaded from keymap.ron, e.g. one from Keymap::load_from
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.modes.set_keymap(keymap);
+    }
+
+    // Terminal columns and rows as of the last update, e.g. to pass on to a pty
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    pub fn printer(&self) -> Printer {
+        Printer(self.inbox.clone())
+    }
+
+    pub fn print(&mut self, output: &str) -> Result<()> {
+        self.print_bytes(output.as_bytes())
+    }
 
-            Ok(message)
-        } else {
-            Ok(None)
+    // Invalid UTF-8 is shown as the output policy says, UTF-8 split across calls is kept whole
+    pub fn print_bytes(&mut self, output: &[u8]) -> Result<()> {
+        // Keep ordering with output queued before this call
+        self.print_queued()?;
+        self.write_output(output)
+    }
+
+    fn listen(&mut self) {
+        if self.reader.is_none() {
+            self.reader = Some(Reader::spawn(self.inbox.clone()));
         }
     }
 
-    pub fn print(&mut self, output: String) -> PyResult<()> {
+    // Show queued output, then handle at most one queued event
+    fn process(&mut self) -> Result<Option<Action>> {
+        self.print_queued()?;
+
+        let event = match self.inbox.pop_event() {
+            Some(event) => event?,
+            None => return Ok(None),
+        };
+        self.shell.clear(&mut self.stdout)?;
+
+        let echoing = self.modes.echoing();
+        let action = match event {
+            event::Event::Key(key) => self.modes.on_key(key),
+            event::Event::Paste(text) => self.modes.on_paste(text),
+            event::Event::Resize(cols, rows) => {
+                self.cols = cols;
+                self.rows = rows;
+                self.shell.resize(cols)?;
+                None
+            }
+            _ => None,
+        };
+
+        self.shell.write(&mut self.stdout, &self.modes)?;
+        self.stdout.flush()?;
+
+        // Only Raw mode echoes, and it only sends text, so WriteBytes never shows here
+        if let (true, Some(Action::Write(text))) = (echoing, &action) {
+            let echo = self.normalizer.visible(text);
+            self.write_text(&echo)?;
+        }
+
+        Ok(action)
+    }
+
+    fn print_queued(&mut self) -> Result<()> {
+        let output = self.inbox.take_output();
+        if !output.is_empty() {
+            self.write_output(&output)?;
+        }
+        Ok(())
+    }
+
+    fn write_output(&mut self, output: &[u8]) -> Result<()> {
+        let output = match self.modes.dump() {
+            true => self.dump.render(output, self.cols),
+            false => self.normalizer.normalize_bytes(output),
+        };
+        self.write_text(&output)
+    }
+
+    fn write_text(&mut self, output: &str) -> Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+
         self.shell.clear(&mut self.stdout)?;
 
         // If last print ended mid-line, move back to the saved column
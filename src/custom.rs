@@ -0,0 +1,96 @@
+use crossterm::{event::KeyEvent, style};
+
+use crate::keymap::{Bindings, Chord, Command};
+use crate::mode::{self, Message, Mode, OnKey, OnPaste};
+use crate::shell;
+
+// Handles keys for a user-defined mode
+pub trait Handler: Send + Sync {
+    fn on_key(&self, key: &str) -> Option<Message>;
+}
+
+pub struct Custom {
+    name: String,
+    color: style::Color,
+    keybinds: Vec<String>,
+    // Selects this mode from Prefix mode
+    key: char,
+    handler: Box<dyn Handler>,
+    bindings: Bindings,
+    hint: Option<String>,
+}
+
+impl Custom {
+    pub fn new(
+        name: impl ToString,
+        key: char,
+        color: style::Color,
+        keybinds: Vec<String>,
+        handler: Box<dyn Handler>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            keybinds,
+            key,
+            handler,
+            bindings: Bindings::default(),
+            hint: None,
+        }
+    }
+
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.hint = bindings.hint(Command::ModePrefix, "Prefix");
+        self.bindings = bindings;
+    }
+
+    pub fn key(&self) -> char {
+        self.key
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl shell::State for Custom {
+    fn color(&self) -> style::Color {
+        self.color
+    }
+
+    fn cursor(&self) -> usize {
+        0
+    }
+
+    fn contents(&self) -> &str {
+        ""
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn keybinds(&self) -> Vec<&str> {
+        self.keybinds
+            .iter()
+            .map(|keybind| keybind.as_str())
+            .chain(self.hint.as_deref())
+            .collect()
+    }
+}
+
+impl OnKey for Custom {
+    fn on_key(&mut self, key: KeyEvent) -> Option<Message> {
+        // Bound keys are handled first, so a handler can't trap the user
+        match self.bindings.get(key) {
+            Some(command) => mode::on_mode_command(command, Mode::Prefix),
+            None => self.handler.on_key(&Chord::from_key(key)?.to_string()),
+        }
+    }
+}
+
+impl OnPaste for Custom {
+    fn on_paste(&mut self, _text: String) -> Option<Message> {
+        None
+    }
+}
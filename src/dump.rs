@@ -0,0 +1,113 @@
+// Renders output as offset, hex and ASCII columns, e.g.
+// 00000010  7e 01 ff 00 41 42 43 44  0a 0d 00 00 00 00 00 00  |~...ABCD........|
+#[derive(Default)]
+pub struct Dump {
+    // Bytes dumped so far
+    offset: usize,
+}
+
+// Widest row narrower than cols, so rows never wrap: bytes per row, and whether the ASCII
+// column fits. Terminals too narrow for even one byte get one anyway.
+fn layout(cols: u16) -> (usize, bool) {
+    [32, 16, 8, 4, 2, 1]
+        .into_iter()
+        .map(|len| (len, true))
+        .chain([(1, false)])
+        .find(|(len, ascii)| row_width(*len, *ascii) < cols as usize)
+        .unwrap_or((1, false))
+}
+
+// Offset, hex columns with a gap every 8 bytes, then the ASCII column
+fn row_width(len: usize, ascii: bool) -> usize {
+    let hex = 10 + 3 * len + len.div_ceil(8) - 2;
+    match ascii {
+        true => hex + 2 + len + 2,
+        false => hex,
+    }
+}
+
+impl Dump {
+    // Rows stay aligned to the row length, with blank cells before a row's first byte
+    pub fn render(&mut self, bytes: &[u8], cols: u16) -> String {
+        let (len, show_ascii) = layout(cols);
+        let mut output = String::new();
+        let mut bytes = bytes;
+
+        while !bytes.is_empty() {
+            let base = self.offset - self.offset % len;
+            let skip = self.offset - base;
+            let (row, rest) = bytes.split_at(bytes.len().min(len - skip));
+
+            let mut hex = String::new();
+            let mut ascii = String::new();
+            for index in 0..len {
+                if index > 0 && index % 8 == 0 {
+                    hex.push(' ');
+                }
+                match index.checked_sub(skip).and_then(|index| row.get(index)) {
+                    Some(byte) => {
+                        hex.push_str(&format!("{:02x} ", byte));
+                        ascii.push(match byte {
+                            0x20..=0x7e => *byte as char,
+                            _ => '.',
+                        });
+                    }
+                    None => {
+                        hex.push_str("   ");
+                        ascii.push(' ');
+                    }
+                }
+            }
+
+            match show_ascii {
+                true => output.push_str(&format!("{:08x}  {} |{}|\n", base, hex, ascii)),
+                false => output.push_str(&format!("{:08x}  {}\n", base, hex.trim_end())),
+            }
+            self.offset += row.len();
+            bytes = rest;
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{layout, Dump};
+
+    #[test]
+    fn rows() {
+        let mut dump = Dump::default();
+        assert_eq!(
+            "00000000  7e 01 41 42  |~.AB|\n00000004  ff           |.   |\n",
+            dump.render(b"\x7e\x01AB\xff", 30)
+        );
+        // Continues the partial row
+        assert_eq!("00000004     0a 43     | .C |\n", dump.render(b"\nC", 30));
+    }
+
+    #[test]
+    fn width() {
+        assert_eq!((16, true), layout(80));
+        assert_eq!((32, true), layout(160));
+        assert_eq!((8, true), layout(50));
+        // Rows exactly as wide as the terminal would wrap
+        assert_eq!((2, true), layout(29));
+        assert_eq!((1, false), layout(15));
+        assert_eq!((1, false), layout(10));
+    }
+
+    #[test]
+    fn narrow_rows() {
+        let mut dump = Dump::default();
+        assert_eq!(
+            "00000000  7e 01  |~.|\n00000002  41     |A |\n",
+            dump.render(b"\x7e\x01A", 24)
+        );
+        assert_eq!("00000000  7e\n", Dump::default().render(b"\x7e", 13));
+        for cols in [13, 20, 28] {
+            let output = Dump::default().render(b"\x7e\x01ABCDEF", cols);
+            assert!(output.lines().all(|row| row.len() < cols as usize));
+        }
+    }
+}
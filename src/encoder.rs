@@ -0,0 +1,207 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+// Escape sequences Raw mode sends for keys
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyEncoding {
+    // No modifier parameters, vt220 style editing and function keys
+    Vt100,
+    #[default]
+    Xterm,
+    // Xterm with application cursor keys, e.g. ESC O A for up
+    Application,
+}
+
+impl FromStr for KeyEncoding {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "vt100" => Ok(Self::Vt100),
+            "xterm" => Ok(Self::Xterm),
+            "application" => Ok(Self::Application),
+            _ => Err(Error::Value(format!(
+                "unknown key encoding {:?}, expected \"vt100\", \"xterm\" or \"application\"",
+                value
+            ))),
+        }
+    }
+}
+
+// Final character of keys sent as ESC [ x or ESC O x
+fn cursor_key(code: KeyCode) -> Option<char> {
+    match code {
+        KeyCode::Up => Some('A'),
+        KeyCode::Down => Some('B'),
+        KeyCode::Right => Some('C'),
+        KeyCode::Left => Some('D'),
+        KeyCode::Home => Some('H'),
+        KeyCode::End => Some('F'),
+        _ => None,
+    }
+}
+
+// Number of keys sent as ESC [ n ~
+fn tilde_key(code: KeyCode) -> Option<u8> {
+    match code {
+        KeyCode::Insert => Some(2),
+        KeyCode::Delete => Some(3),
+        KeyCode::PageUp => Some(5),
+        KeyCode::PageDown => Some(6),
+        KeyCode::F(5) => Some(15),
+        KeyCode::F(n @ 6..=10) => Some(n + 11),
+        KeyCode::F(n @ 11..=12) => Some(n + 12),
+        _ => None,
+    }
+}
+
+// Byte sent for ctrl+c, including the punctuation controls like ctrl+]
+fn control(c: char) -> Option<char> {
+    let byte = match c.to_ascii_lowercase() {
+        c @ 'a'..='z' => c as u8 - b'a' + 1,
+        '@' | ' ' | '2' => 0,
+        '[' | '3' => 0x1b,
+        '\\' | '4' => 0x1c,
+        ']' | '5' => 0x1d,
+        '^' | '6' => 0x1e,
+        '_' | '7' | '-' => 0x1f,
+        '?' | '8' => 0x7f,
+        _ => return None,
+    };
+    Some(byte as char)
+}
+
+// Bytes for a key, except Enter which Raw mode sends as the terminator
+pub fn encode(key: KeyEvent, encoding: KeyEncoding) -> Option<String> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    // xterm modifier parameter, 1 for none
+    let modifier = 1 + shift as u8 + 2 * alt as u8 + 4 * ctrl as u8;
+    let modified = modifier > 1 && encoding != KeyEncoding::Vt100;
+
+    // Keys without their own modified form get ESC for alt
+    let meta = |bytes: String| match alt {
+        true => Some(format!("\x1b{}", bytes)),
+        false => Some(bytes),
+    };
+
+    if let Some(c) = cursor_key(key.code) {
+        let sequence = match (encoding, key.code) {
+            _ if modified => format!("\x1b[1;{}{}", modifier, c),
+            (KeyEncoding::Vt100, KeyCode::Home) => "\x1b[1~".into(),
+            (KeyEncoding::Vt100, KeyCode::End) => "\x1b[4~".into(),
+            (KeyEncoding::Application, _) => format!("\x1bO{}", c),
+            _ => format!("\x1b[{}", c),
+        };
+        return match modified {
+            true => Some(sequence),
+            false => meta(sequence),
+        };
+    }
+
+    if let Some(n) = tilde_key(key.code) {
+        return match modified {
+            true => Some(format!("\x1b[{};{}~", n, modifier)),
+            false => meta(format!("\x1b[{}~", n)),
+        };
+    }
+
+    match key.code {
+        KeyCode::F(n @ 1..=4) => {
+            let c = (b'P' + n - 1) as char;
+            match modified {
+                true => Some(format!("\x1b[1;{}{}", modifier, c)),
+                false => meta(format!("\x1bO{}", c)),
+            }
+        }
+        KeyCode::Char(c) if ctrl => meta(control(c)?.into()),
+        KeyCode::Char(c) => meta(c.into()),
+        KeyCode::Backspace if ctrl => meta("\x08".into()),
+        KeyCode::Backspace => meta("\x7f".into()),
+        KeyCode::Tab if shift => Some("\x1b[Z".into()),
+        KeyCode::Tab => meta("\t".into()),
+        KeyCode::BackTab => Some("\x1b[Z".into()),
+        KeyCode::Esc => meta("\x1b".into()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode, KeyEncoding};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn xterm(code: KeyCode, modifiers: KeyModifiers) -> Option<String> {
+        encode(KeyEvent::new(code, modifiers), KeyEncoding::Xterm)
+    }
+
+    #[test]
+    fn chars() {
+        assert_eq!(
+            Some("a".into()),
+            xterm(KeyCode::Char('a'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            Some("\x01".into()),
+            xterm(KeyCode::Char('a'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            Some("\x1bx".into()),
+            xterm(KeyCode::Char('x'), KeyModifiers::ALT)
+        );
+        // crossterm reports ctrl+\ as ctrl+4
+        assert_eq!(
+            Some("\x1c".into()),
+            xterm(KeyCode::Char('4'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            Some("\x1d".into()),
+            xterm(KeyCode::Char(']'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn cursor_keys() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(Some("\x1b[D".into()), xterm(KeyCode::Left, none));
+        assert_eq!(
+            Some("\x1b[1;5C".into()),
+            xterm(KeyCode::Right, KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            Some("\x1b[3;2~".into()),
+            xterm(KeyCode::Delete, KeyModifiers::SHIFT)
+        );
+
+        let up = KeyEvent::new(KeyCode::Up, none);
+        assert_eq!(Some("\x1bOA".into()), encode(up, KeyEncoding::Application));
+        let home = KeyEvent::new(KeyCode::Home, KeyModifiers::SHIFT);
+        assert_eq!(Some("\x1b[1~".into()), encode(home, KeyEncoding::Vt100));
+    }
+
+    #[test]
+    fn function_keys() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(Some("\x1bOP".into()), xterm(KeyCode::F(1), none));
+        assert_eq!(
+            Some("\x1b[1;3S".into()),
+            xterm(KeyCode::F(4), KeyModifiers::ALT)
+        );
+        assert_eq!(Some("\x1b[15~".into()), xterm(KeyCode::F(5), none));
+        assert_eq!(Some("\x1b[21~".into()), xterm(KeyCode::F(10), none));
+        assert_eq!(
+            Some("\x1b[24;5~".into()),
+            xterm(KeyCode::F(12), KeyModifiers::CONTROL)
+        );
+    }
+}
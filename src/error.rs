@@ -15,6 +15,8 @@ pub enum Error {
     Path(String),
     #[error("invalid system time: {0}")]
     SystemTime(#[from] SystemTimeError),
+    #[error("invalid value: {0}")]
+    Value(String),
 }
 pub type Result<T, E = Error> = result::Result<T, E>;
 
@@ -0,0 +1,151 @@
+use crossterm::{event::KeyEvent, style};
+use std::ops::Range;
+
+use crate::history::History;
+use crate::keymap::Keymap;
+use crate::mode::{Line, Message, OnKey, OnPaste};
+use crate::shell::{self, State};
+
+// Parses bytes like "7e 01 ff", "7e01ff" or "0x7e 0x01", or returns the char range of the first bad token
+pub fn parse(input: &str) -> std::result::Result<Vec<u8>, Range<usize>> {
+    let mut bytes = Vec::new();
+    let mut start = 0;
+
+    for token in input.split(|c: char| c.is_whitespace() || c == ',') {
+        let len = token.chars().count();
+        let range = start..start + len;
+        start += len + 1;
+
+        let digits = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        let hex = digits.chars().all(|c| c.is_ascii_hexdigit());
+        if !hex || digits.len() % 2 != 0 || (token != digits && digits.is_empty()) {
+            return Err(range);
+        }
+
+        for pair in digits.as_bytes().chunks(2) {
+            let high = (pair[0] as char).to_digit(16).unwrap_or_default();
+            let low = (pair[1] as char).to_digit(16).unwrap_or_default();
+            bytes.push((high * 16 + low) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+// Line editing for hex bytes, sent as one write on Enter
+pub struct Hex {
+    line: Line,
+}
+
+impl Hex {
+    pub fn new(history: History) -> Self {
+        Self {
+            line: Line::new(history),
+        }
+    }
+
+    pub fn line_mut(&mut self) -> &mut Line {
+        &mut self.line
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.line.set_keymap(keymap);
+    }
+}
+
+impl shell::State for Hex {
+    fn color(&self) -> style::Color {
+        style::Color::Cyan
+    }
+
+    fn cursor(&self) -> usize {
+        self.line.cursor()
+    }
+
+    fn contents(&self) -> &str {
+        self.line.contents()
+    }
+
+    fn name(&self) -> &str {
+        match self.line.name() {
+            "LINE" => "HEX",
+            "LINE:I" => "HEX:I",
+            "LINE:N" => "HEX:N",
+            name => name,
+        }
+    }
+
+    fn keybinds(&self) -> Vec<&str> {
+        self.line.keybinds()
+    }
+
+    // The first bad token, or the search match
+    fn highlight(&self) -> Option<Range<usize>> {
+        match self.line.is_editing() {
+            true => parse(self.line.contents()).err(),
+            false => self.line.highlight(),
+        }
+    }
+
+    fn suggestion(&self) -> &str {
+        self.line.suggestion()
+    }
+
+    fn menu(&self) -> &[String] {
+        self.line.menu()
+    }
+
+    fn menu_selected(&self) -> Option<usize> {
+        self.line.menu_selected()
+    }
+}
+
+impl OnPaste for Hex {
+    fn on_paste(&mut self, text: String) -> Option<Message> {
+        // Multi-line dumps are one frame
+        self.line.on_paste(text.replace(['\r', '\n'], " "))
+    }
+}
+
+impl OnKey for Hex {
+    fn on_key(&mut self, key: KeyEvent) -> Option<Message> {
+        // Keep bad input for fixing, instead of sending or adding a row
+        if self.line.is_submit(key) && parse(self.line.contents()).is_err() {
+            return None;
+        }
+
+        match self.line.on_key(key) {
+            Some(Message::Writeline(line)) => match parse(&line) {
+                Ok(bytes) if bytes.is_empty() => None,
+                Ok(bytes) => Some(Message::WriteBytes(bytes)),
+                Err(_) => None,
+            },
+            message => message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn parse_bytes() {
+        assert_eq!(Ok(vec![0x7e, 0x01, 0xff, 0x00]), parse("7e 01 ff 00"));
+        assert_eq!(Ok(vec![0x7e, 0x01, 0xff]), parse("7E01ff"));
+        assert_eq!(Ok(vec![0x7e, 0x01]), parse("0x7e, 0x01"));
+        assert_eq!(Ok(vec![]), parse("  "));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(3..6), parse("7e 0f1 ff"));
+        assert_eq!(Err(0..2), parse("zz"));
+        assert_eq!(Err(3..5), parse("01 0x"));
+        assert_eq!(Err(3..5), parse("01 é1"));
+        assert_eq!(Err(0..2), parse("+f"));
+    }
+}
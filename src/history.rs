@@ -7,7 +7,6 @@ use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::time::SystemTime;
-use std::u128;
 
 use crate::error::Result;
 use crate::path;
@@ -26,16 +25,28 @@ pub struct Entry {
     ts: u128,
 }
 
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+// Nanoseconds since the epoch, as stored in entries
+pub fn now() -> Result<u128> {
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    Ok(now.as_nanos())
+}
+
 impl Entry {
-    #[allow(dead_code)] // Unused until Ctrl+R search implemented
     fn recency_factor(&self, now: u128) -> u64 {
-        match now.saturating_sub(self.ts) {
+        // Buckets are in seconds, timestamps in nanoseconds
+        match now.saturating_sub(self.ts) / NANOS_PER_SECOND {
             0..3600 => 8,
             3600..86400 => 4,
             86400..604800 => 2,
             604800..=u128::MAX => 1,
         }
     }
+
+    pub fn score(&self, now: u128) -> u64 {
+        self.count.saturating_mul(self.recency_factor(now))
+    }
 }
 
 impl Default for History {
@@ -60,12 +71,12 @@ impl History {
 
     pub fn load_by_name(name: impl AsRef<str>) -> Result<Self> {
         let path = path::history_dir()?.join(format!("{}.ron", name.as_ref()));
-        Ok(Self::load(&path)?)
+        Self::load(&path)
     }
 
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
-        let file = fs::File::open(&path)?;
+        let file = fs::File::open(path)?;
         let contents = io::read_to_string(file)?;
         let entries = ron::from_str(contents.as_str())?;
 
@@ -92,8 +103,7 @@ impl History {
     }
 
     pub fn update(&mut self, cmd: impl AsRef<str>) -> Result<()> {
-        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-        self.add(cmd, now.as_nanos());
+        self.add(cmd, now()?);
         self.sort();
         self.write()?;
         Ok(())
@@ -124,4 +134,114 @@ impl History {
     pub fn entries(&mut self) -> &Vec<Entry> {
         &self.entries
     }
+
+    // Entries containing query, best score first, most recent first on ties
+    pub fn search(&self, query: &str, now: u128) -> Vec<&Entry> {
+        let mut matches: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.cmd.contains(query))
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score(now)
+                .cmp(&a.score(now))
+                .then_with(|| b.ts.cmp(&a.ts))
+        });
+
+        matches
+    }
+
+    // Best entry extending prefix, for inline autosuggestions
+    pub fn suggest(&self, prefix: &str, now: u128) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.cmd.len() > prefix.len() && entry.cmd.starts_with(prefix))
+            .max_by(|a, b| {
+                a.score(now)
+                    .cmp(&b.score(now))
+                    .then_with(|| a.ts.cmp(&b.ts))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::History;
+
+    const HOUR: u128 = 3_600_000_000_000;
+
+    fn cmds(history: &History, query: &str, now: u128) -> Vec<String> {
+        history
+            .search(query, now)
+            .into_iter()
+            .map(|entry| entry.cmd.clone())
+            .collect()
+    }
+
+    #[test]
+    fn search_filters_by_substring() {
+        let mut history = History::default();
+        history.add("reg read 0x10", 0);
+        history.add("dev list", 0);
+
+        assert_eq!(vec!["reg read 0x10"], cmds(&history, "read", 0));
+    }
+
+    #[test]
+    fn search_ranks_by_count_and_recency() {
+        let mut history = History::default();
+        let now = 1000 * HOUR;
+
+        // Used often, but a month ago
+        for _ in 0..3 {
+            history.add("reg read old", now - 720 * HOUR);
+        }
+        // Used once, just now
+        history.add("reg read new", now);
+
+        // 3 * 1 < 1 * 8
+        assert_eq!(
+            vec!["reg read new", "reg read old"],
+            cmds(&history, "reg", now)
+        );
+    }
+
+    #[test]
+    fn recent_use_outranks_higher_count() {
+        let mut history = History::default();
+        let now = 1000 * HOUR;
+
+        // Used twice, a month ago
+        history.add("reg read old", now - 720 * HOUR);
+        history.add("reg read old", now - 720 * HOUR);
+        // Used once, a minute ago
+        history.add("reg read new", now - HOUR / 60);
+
+        assert_eq!(
+            vec!["reg read new", "reg read old"],
+            cmds(&history, "reg", now)
+        );
+    }
+
+    #[test]
+    fn search_breaks_ties_by_recency() {
+        let mut history = History::default();
+        history.add("a1", 1);
+        history.add("a2", 2);
+
+        assert_eq!(vec!["a2", "a1"], cmds(&history, "a", 2));
+    }
+
+    #[test]
+    fn suggest_requires_longer_prefix_match() {
+        let mut history = History::default();
+        history.add("reg", 0);
+        history.add("reg read", 0);
+        history.add("dev reg", 0);
+
+        let suggestion = history.suggest("reg", 0).map(|entry| entry.cmd.as_str());
+        assert_eq!(Some("reg read"), suggestion);
+        assert!(history.suggest("reg read", 0).is_none());
+    }
 }
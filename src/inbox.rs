@@ -0,0 +1,183 @@
+use crossterm::event::{self, Event};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// How long the reader holds crossterm's event lock at a time
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Default)]
+struct Queued {
+    events: VecDeque<io::Result<Event>>,
+    output: Vec<u8>,
+    // Task waiting in Ready, if any
+    waker: Option<Waker>,
+}
+
+impl Queued {
+    fn is_empty(&self) -> bool {
+        self.events.is_empty() && self.output.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    queued: Mutex<Queued>,
+    pushed: Condvar,
+}
+
+// Terminal events and printed bytes waiting for the console, filled from any thread
+#[derive(Clone, Default)]
+pub struct Inbox(Arc<Shared>);
+
+impl Inbox {
+    fn lock(&self) -> MutexGuard<'_, Queued> {
+        // Queued stays consistent even if a pusher panicked
+        self.0.queued.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn push(&self, push: impl FnOnce(&mut Queued)) {
+        let mut queued = self.lock();
+        push(&mut queued);
+        let waker = queued.waker.take();
+        drop(queued);
+
+        self.0.pushed.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub fn push_event(&self, event: io::Result<Event>) {
+        self.push(|queued| queued.events.push_back(event));
+    }
+
+    pub fn push_output(&self, output: &[u8]) {
+        self.push(|queued| queued.output.extend_from_slice(output));
+    }
+
+    pub fn pop_event(&self) -> Option<io::Result<Event>> {
+        self.lock().events.pop_front()
+    }
+
+    pub fn take_output(&self) -> Vec<u8> {
+        mem::take(&mut self.lock().output)
+    }
+
+    // Block until something is queued, or timeout passes
+    pub fn wait(&self, timeout: Duration) {
+        let queued = self.lock();
+        let _ = self
+            .0
+            .pushed
+            .wait_timeout_while(queued, timeout, |queued| queued.is_empty());
+    }
+
+    // Resolves once something is queued
+    pub fn ready(&self) -> Ready {
+        Ready(self.clone())
+    }
+}
+
+pub struct Ready(Inbox);
+
+impl Future for Ready {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut queued = self.0.lock();
+        if queued.is_empty() {
+            queued.waker = Some(cx.waker().clone());
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+// Reads terminal events into an inbox on a background thread
+pub struct Reader {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Reader {
+    pub fn spawn(inbox: Inbox) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let running = running.clone();
+            thread::spawn(move || {
+                // Poll rather than block in read, so cursor::position() can get the lock
+                while running.load(Ordering::Relaxed) {
+                    match event::poll(POLL_INTERVAL) {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(err) => return inbox.push_event(Err(err)),
+                    }
+
+                    let event = event::read();
+                    let failed = event.is_err();
+                    inbox.push_event(event);
+                    if failed {
+                        return;
+                    }
+                }
+            })
+        };
+
+        Self {
+            running,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                log::warn!("event reader panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Inbox;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn wait_wakes_on_output() {
+        let inbox = Inbox::default();
+
+        let pusher = inbox.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            pusher.push_output(b"hello");
+        });
+
+        let start = Instant::now();
+        inbox.wait(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(b"hello", &inbox.take_output()[..]);
+        assert!(inbox.take_output().is_empty());
+    }
+
+    #[test]
+    fn wait_times_out() {
+        let inbox = Inbox::default();
+        inbox.wait(Duration::from_millis(1));
+        assert!(inbox.pop_event().is_none());
+    }
+}
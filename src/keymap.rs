@@ -0,0 +1,604 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::path;
+use crate::{Error, Result};
+
+// Named actions that key chords can be bound to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Quit,
+    ModePrefix,
+    ModeLine,
+    ModeRaw,
+    ModeHex,
+    // Leave Prefix mode
+    ModeReturn,
+    // Toggle local echo in Raw mode
+    RawEcho,
+    // Toggle showing output as a hex dump
+    OutputDump,
+    LineBeginning,
+    LineEnd,
+    LineSubmit,
+    LineNewline,
+    LineEscape,
+    LineBackwardChar,
+    LineForwardChar,
+    LineBackwardWord,
+    LineForwardWord,
+    LineHistoryPrev,
+    LineHistoryNext,
+    LineComplete,
+    LineDeleteBackward,
+    LineDeleteForward,
+    LineKillWordBackward,
+    LineKillWordForward,
+    LineKillStart,
+    LineKillEnd,
+    LineYank,
+    LineYankPop,
+    LineUndo,
+    LineRedo,
+    LineClear,
+    LineSearch,
+    SearchOlder,
+    SearchNewer,
+    SearchCancel,
+    // Send a multi-line paste as separate lines
+    PasteSend,
+    // Insert a multi-line paste into the line instead
+    PasteEdit,
+    PasteCancel,
+    MenuNext,
+    MenuPrev,
+    MenuClose,
+}
+
+const COMMANDS: &[(&str, Command)] = &[
+    ("quit", Command::Quit),
+    ("mode.prefix", Command::ModePrefix),
+    ("mode.line", Command::ModeLine),
+    ("mode.raw", Command::ModeRaw),
+    ("mode.hex", Command::ModeHex),
+    ("mode.return", Command::ModeReturn),
+    ("raw.echo", Command::RawEcho),
+    ("output.dump", Command::OutputDump),
+    ("line.beginning", Command::LineBeginning),
+    ("line.end", Command::LineEnd),
+    ("line.submit", Command::LineSubmit),
+    ("line.newline", Command::LineNewline),
+    ("line.escape", Command::LineEscape),
+    ("line.backward-char", Command::LineBackwardChar),
+    ("line.forward-char", Command::LineForwardChar),
+    ("line.backward-word", Command::LineBackwardWord),
+    ("line.forward-word", Command::LineForwardWord),
+    ("line.history-prev", Command::LineHistoryPrev),
+    ("line.history-next", Command::LineHistoryNext),
+    ("line.complete", Command::LineComplete),
+    ("line.delete-backward", Command::LineDeleteBackward),
+    ("line.delete-forward", Command::LineDeleteForward),
+    ("line.kill-word-backward", Command::LineKillWordBackward),
+    ("line.kill-word-forward", Command::LineKillWordForward),
+    ("line.kill-start", Command::LineKillStart),
+    ("line.kill-end", Command::LineKillEnd),
+    ("line.yank", Command::LineYank),
+    ("line.yank-pop", Command::LineYankPop),
+    ("line.undo", Command::LineUndo),
+    ("line.redo", Command::LineRedo),
+    ("line.clear", Command::LineClear),
+    ("line.search", Command::LineSearch),
+    ("search.older", Command::SearchOlder),
+    ("search.newer", Command::SearchNewer),
+    ("search.cancel", Command::SearchCancel),
+    ("paste.send", Command::PasteSend),
+    ("paste.edit", Command::PasteEdit),
+    ("paste.cancel", Command::PasteCancel),
+    ("menu.next", Command::MenuNext),
+    ("menu.prev", Command::MenuPrev),
+    ("menu.close", Command::MenuClose),
+];
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        COMMANDS
+            .iter()
+            .find(|(name, _)| *name == value)
+            .map(|(_, command)| *command)
+            .ok_or_else(|| Error::Value(format!("unknown action {:?}", value)))
+    }
+}
+
+// A key with modifiers, written like "ctrl+a", "alt+left" or "f1"
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Chord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Chord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let code = match (code, modifiers.contains(KeyModifiers::CONTROL)) {
+            // Terminals send ^\ ^] ^^ ^_ as the same bytes as ^4 ^5 ^6 ^7
+            (KeyCode::Char('4'), true) => KeyCode::Char('\\'),
+            (KeyCode::Char('5'), true) => KeyCode::Char(']'),
+            (KeyCode::Char('6'), true) => KeyCode::Char('^'),
+            (KeyCode::Char('7'), true) => KeyCode::Char('_'),
+            (code, _) => code,
+        };
+
+        // Terminals report shift+tab as BackTab, with or without shift
+        let (code, modifiers) = match code {
+            KeyCode::BackTab => (KeyCode::Tab, modifiers | KeyModifiers::SHIFT),
+            code => (code, modifiers),
+        };
+
+        // Shifted characters are already uppercase
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        Self { code, modifiers }
+    }
+
+    // Only presses of keys that have a name
+    pub fn from_key(key: KeyEvent) -> Option<Self> {
+        let named = match key.code {
+            KeyCode::Char(_) | KeyCode::F(_) => true,
+            code => NAMED.iter().any(|(_, named)| *named == code),
+        };
+
+        match (key.kind, named) {
+            (KeyEventKind::Press, true) => Some(Self::new(key.code, key.modifiers)),
+            _ => None,
+        }
+    }
+
+    // Compact form for footer hints, e.g. "^D", "M-y" or "S-Tab"
+    pub fn hint(&self) -> String {
+        let name = match self.code {
+            KeyCode::Char(_) | KeyCode::F(_) => {
+                Self::new(self.code, KeyModifiers::NONE).to_string()
+            }
+            code => capitalize(&Self::new(code, KeyModifiers::NONE).to_string()),
+        };
+
+        match (self.modifiers, self.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char(c)) => format!("^{}", c.to_ascii_uppercase()),
+            (KeyModifiers::ALT, _) => format!("M-{}", name),
+            (KeyModifiers::SHIFT, _) => format!("S-{}", name),
+            (KeyModifiers::NONE, _) => name,
+            _ => self.to_string(),
+        }
+    }
+}
+
+// e.g. "Esc" for "esc"
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => match NAMED.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+const MODIFIERS: &[(&str, KeyModifiers)] = &[
+    ("ctrl+", KeyModifiers::CONTROL),
+    ("alt+", KeyModifiers::ALT),
+    ("shift+", KeyModifiers::SHIFT),
+];
+
+const NAMED: &[(&str, KeyCode)] = &[
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("insert", KeyCode::Insert),
+    ("delete", KeyCode::Delete),
+];
+
+impl FromStr for Chord {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut rest = value;
+        let mut modifiers = KeyModifiers::NONE;
+
+        // Checked against the remainder so "ctrl++" binds the plus key
+        while let Some((prefix, modifier)) = MODIFIERS
+            .iter()
+            .find(|(prefix, _)| rest.len() > prefix.len() && rest.starts_with(prefix))
+        {
+            modifiers |= *modifier;
+            rest = &rest[prefix.len()..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ if rest == "space" => KeyCode::Char(' '),
+            _ => match NAMED.iter().find(|(name, _)| *name == rest) {
+                Some((_, code)) => *code,
+                None => match rest.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) => KeyCode::F(n),
+                    None => return Err(Error::Value(format!("unknown key {:?}", value))),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+// Chords bound in one mode, earlier bindings shown first in hints
+#[derive(Clone, Debug, Default)]
+pub struct Bindings(Vec<(Chord, Command)>);
+
+impl Bindings {
+    fn from_defaults(defaults: &[(&str, Command)]) -> Self {
+        Self(
+            defaults
+                .iter()
+                .map(|(chord, command)| (chord.parse().expect("invalid default chord"), *command))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: KeyEvent) -> Option<Command> {
+        let chord = Chord::from_key(key)?;
+        self.get_chord(chord)
+    }
+
+    pub fn get_chord(&self, chord: Chord) -> Option<Command> {
+        self.0
+            .iter()
+            .find(|(bound, _)| *bound == chord)
+            .map(|(_, command)| *command)
+    }
+
+    // e.g. "^D Quit", or None if nothing is bound to command
+    pub fn hint(&self, command: Command, label: &str) -> Option<String> {
+        self.0
+            .iter()
+            .find(|(_, bound)| *bound == command)
+            .map(|(chord, _)| format!("{} {}", chord.hint(), label))
+    }
+
+    // Hints for the commands that are bound, in order
+    pub fn hints(&self, labels: &[(Command, &str)]) -> Vec<String> {
+        labels
+            .iter()
+            .filter_map(|(command, label)| self.hint(*command, label))
+            .collect()
+    }
+
+    // Overrides take precedence, and show first in hints
+    fn bind(&mut self, chord: Chord, command: Option<Command>) {
+        self.0.retain(|(bound, _)| *bound != chord);
+        if let Some(command) = command {
+            self.0.insert(0, (chord, command));
+        }
+    }
+
+    fn apply(&mut self, overrides: BTreeMap<String, String>) -> Result<()> {
+        for (chord, command) in overrides {
+            let command = match command.as_str() {
+                "none" => None,
+                command => Some(command.parse()?),
+            };
+            self.bind(chord.parse()?, command);
+        }
+        Ok(())
+    }
+}
+
+const LINE: &[(&str, Command)] = &[
+    ("ctrl+d", Command::Quit),
+    ("ctrl+r", Command::LineSearch),
+    ("ctrl+\\", Command::ModePrefix),
+    ("home", Command::LineBeginning),
+    ("ctrl+a", Command::LineBeginning),
+    ("end", Command::LineEnd),
+    ("ctrl+e", Command::LineEnd),
+    ("enter", Command::LineSubmit),
+    ("alt+enter", Command::LineNewline),
+    ("esc", Command::LineEscape),
+    ("left", Command::LineBackwardChar),
+    ("right", Command::LineForwardChar),
+    ("ctrl+left", Command::LineBackwardWord),
+    ("alt+left", Command::LineBackwardWord),
+    ("alt+b", Command::LineBackwardWord),
+    ("ctrl+right", Command::LineForwardWord),
+    ("alt+right", Command::LineForwardWord),
+    ("alt+f", Command::LineForwardWord),
+    ("up", Command::LineHistoryPrev),
+    ("down", Command::LineHistoryNext),
+    ("tab", Command::LineComplete),
+    ("backspace", Command::LineDeleteBackward),
+    // Many terminals send ^H for Backspace
+    ("ctrl+h", Command::LineDeleteBackward),
+    ("delete", Command::LineDeleteForward),
+    // Only reported as such by terminals that tell it apart from Backspace
+    ("ctrl+backspace", Command::LineKillWordBackward),
+    ("ctrl+w", Command::LineKillWordBackward),
+    ("alt+backspace", Command::LineKillWordBackward),
+    ("alt+d", Command::LineKillWordForward),
+    ("ctrl+u", Command::LineKillStart),
+    ("ctrl+k", Command::LineKillEnd),
+    ("ctrl+y", Command::LineYank),
+    ("alt+y", Command::LineYankPop),
+    ("ctrl+_", Command::LineUndo),
+    ("ctrl+z", Command::LineUndo),
+    ("alt+_", Command::LineRedo),
+    ("ctrl+c", Command::LineClear),
+];
+
+const RAW: &[(&str, Command)] = &[("ctrl+\\", Command::ModePrefix)];
+
+const PREFIX: &[(&str, Command)] = &[
+    ("q", Command::Quit),
+    ("r", Command::ModeRaw),
+    ("l", Command::ModeLine),
+    ("x", Command::ModeHex),
+    ("e", Command::RawEcho),
+    ("d", Command::OutputDump),
+    ("ctrl+\\", Command::ModeReturn),
+];
+
+const CUSTOM: &[(&str, Command)] = &[("ctrl+\\", Command::ModePrefix)];
+
+// Line mode while searching history, where other keys edit the query
+const SEARCH: &[(&str, Command)] = &[
+    ("ctrl+r", Command::SearchOlder),
+    ("ctrl+s", Command::SearchNewer),
+    ("esc", Command::SearchCancel),
+    ("ctrl+c", Command::SearchCancel),
+    ("ctrl+g", Command::SearchCancel),
+];
+
+// Line mode while confirming a multi-line paste
+const PASTE: &[(&str, Command)] = &[
+    ("y", Command::PasteSend),
+    ("enter", Command::PasteSend),
+    ("n", Command::PasteEdit),
+    ("esc", Command::PasteCancel),
+    ("ctrl+c", Command::PasteCancel),
+];
+
+// Line mode while the completion menu is open, where other keys close it and edit the line
+const MENU: &[(&str, Command)] = &[
+    ("tab", Command::MenuNext),
+    ("down", Command::MenuNext),
+    ("right", Command::MenuNext),
+    ("shift+tab", Command::MenuPrev),
+    ("up", Command::MenuPrev),
+    ("left", Command::MenuPrev),
+    ("esc", Command::MenuClose),
+    ("enter", Command::MenuClose),
+];
+
+// Overrides from keymap.ron, e.g. `(prefix: {"ctrl+]": "mode.return"})`. "none" unbinds.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Overrides {
+    line: BTreeMap<String, String>,
+    raw: BTreeMap<String, String>,
+    prefix: BTreeMap<String, String>,
+    custom: BTreeMap<String, String>,
+    search: BTreeMap<String, String>,
+    paste: BTreeMap<String, String>,
+    menu: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    pub line: Bindings,
+    pub raw: Bindings,
+    pub prefix: Bindings,
+    // Shared by all custom modes
+    pub custom: Bindings,
+    pub search: Bindings,
+    pub paste: Bindings,
+    pub menu: Bindings,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            line: Bindings::from_defaults(LINE),
+            raw: Bindings::from_defaults(RAW),
+            prefix: Bindings::from_defaults(PREFIX),
+            custom: Bindings::from_defaults(CUSTOM),
+            search: Bindings::from_defaults(SEARCH),
+            paste: Bindings::from_defaults(PASTE),
+            menu: Bindings::from_defaults(MENU),
+        }
+    }
+}
+
+impl Keymap {
+    // Defaults with overrides from the data directory, if there are any
+    pub fn load() -> Result<Self> {
+        let path = path::data_dir()?.join("keymap.ron");
+
+        match Self::load_from(&path) {
+            Err(Error::Io(io::ErrorKind::NotFound)) => Ok(Self::default()),
+            result => result,
+        }
+    }
+
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let overrides: Overrides = ron::from_str(&contents)?;
+
+        let mut keymap = Self::default();
+        keymap.line.apply(overrides.line)?;
+        keymap.raw.apply(overrides.raw)?;
+        keymap.prefix.apply(overrides.prefix)?;
+        keymap.custom.apply(overrides.custom)?;
+        keymap.search.apply(overrides.search)?;
+        keymap.paste.apply(overrides.paste)?;
+        keymap.menu.apply(overrides.menu)?;
+
+        log::debug!("loaded keymap from {:?}", path);
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Chord, Command, Keymap, Overrides};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> String {
+        Chord::new(code, modifiers).to_string()
+    }
+
+    #[test]
+    fn chord_names() {
+        assert_eq!("a", chord(KeyCode::Char('a'), KeyModifiers::NONE));
+        assert_eq!("A", chord(KeyCode::Char('A'), KeyModifiers::SHIFT));
+        assert_eq!("ctrl+a", chord(KeyCode::Char('a'), KeyModifiers::CONTROL));
+        assert_eq!("ctrl+\\", chord(KeyCode::Char('4'), KeyModifiers::CONTROL));
+        assert_eq!("alt+enter", chord(KeyCode::Enter, KeyModifiers::ALT));
+        assert_eq!("shift+up", chord(KeyCode::Up, KeyModifiers::SHIFT));
+        assert_eq!("f12", chord(KeyCode::F(12), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn chords_round_trip() {
+        for name in [
+            "a",
+            "ctrl+\\",
+            "ctrl++",
+            "+",
+            "alt+left",
+            "shift+tab",
+            "f1",
+            "space",
+        ] {
+            assert_eq!(name, name.parse::<Chord>().unwrap().to_string());
+        }
+        assert!("ctrl+nope".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn hints() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            Some("^D Quit".to_string()),
+            keymap.line.hint(Command::Quit, "Quit")
+        );
+        assert_eq!(
+            Some("^\\ Prefix".to_string()),
+            keymap.line.hint(Command::ModePrefix, "Prefix")
+        );
+        assert_eq!(
+            Some("q Quit".to_string()),
+            keymap.prefix.hint(Command::Quit, "Quit")
+        );
+        assert_eq!(
+            vec!["Tab Next", "S-Tab Prev", "Esc Close"],
+            keymap.menu.hints(&[
+                (Command::MenuNext, "Next"),
+                (Command::MenuPrev, "Prev"),
+                (Command::MenuClose, "Close"),
+            ])
+        );
+    }
+
+    #[test]
+    fn backtab_is_shift_tab() {
+        let keymap = Keymap::default();
+        let backtab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::NONE);
+        assert_eq!(Some(Command::MenuPrev), keymap.menu.get(backtab));
+    }
+
+    #[test]
+    fn overrides_apply_in_order() {
+        let overrides: Overrides =
+            ron::from_str(r#"(search: {"ctrl+p": "search.older", "ctrl+o": "search.older"})"#)
+                .unwrap();
+
+        // Sorted by chord, so the hint doesn't depend on hash order
+        let mut keymap = Keymap::default();
+        keymap.search.apply(overrides.search).unwrap();
+        assert_eq!(
+            Some("^P Older".to_string()),
+            keymap.search.hint(Command::SearchOlder, "Older")
+        );
+    }
+
+    #[test]
+    fn backspace_chords() {
+        let keymap = Keymap::default();
+        let ctrl_h = KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL);
+        let ctrl_backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::CONTROL);
+        assert_eq!(Some(Command::LineDeleteBackward), keymap.line.get(ctrl_h));
+        assert_eq!(
+            Some(Command::LineKillWordBackward),
+            keymap.line.get(ctrl_backspace)
+        );
+    }
+
+    #[test]
+    fn overrides_rebind_and_unbind() {
+        let overrides: Overrides =
+            ron::from_str(r#"(line: {"ctrl+]": "mode.prefix", "ctrl+\\": "none"})"#).unwrap();
+
+        let mut keymap = Keymap::default();
+        keymap.line.apply(overrides.line).unwrap();
+
+        let prefix = KeyEvent::new(KeyCode::Char('5'), KeyModifiers::CONTROL);
+        let old = KeyEvent::new(KeyCode::Char('4'), KeyModifiers::CONTROL);
+        assert_eq!(Some(Command::ModePrefix), keymap.line.get(prefix));
+        assert_eq!(None, keymap.line.get(old));
+        assert_eq!(
+            Some("^] Prefix".to_string()),
+            keymap.line.hint(Command::ModePrefix, "Prefix")
+        );
+    }
+}
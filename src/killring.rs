@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::ops::Range;
+
+// Oldest kills are dropped past this many entries
+const MAX_KILLS: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+// What the previous key did, so kills and yanks only chain when consecutive
+#[derive(Clone, Debug, Default, PartialEq)]
+enum Last {
+    #[default]
+    Other,
+    Kill,
+    Yank {
+        index: usize,
+        range: Range<usize>,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    previous: Last,
+    current: Last,
+}
+
+impl KillRing {
+    // Called once before handling each key
+    pub fn advance(&mut self) {
+        self.previous = mem::take(&mut self.current);
+    }
+
+    pub fn kill(&mut self, text: &str, direction: Direction) {
+        if self.previous == Last::Kill {
+            // Consecutive kills concatenate into a single entry, like readline
+            if let Some(entry) = self.entries.front_mut() {
+                match direction {
+                    Direction::Forward => entry.push_str(text),
+                    Direction::Backward => entry.insert_str(0, text),
+                }
+            }
+            self.current = Last::Kill;
+        } else if !text.is_empty() {
+            self.entries.push_front(text.to_string());
+            self.entries.truncate(MAX_KILLS);
+            self.current = Last::Kill;
+        }
+    }
+
+    // Most recent kill, to be inserted at the given character index
+    pub fn yank(&mut self, at: usize) -> Option<&str> {
+        let entry = self.entries.front()?;
+        self.current = Last::Yank {
+            index: 0,
+            range: at..at + entry.chars().count(),
+        };
+        Some(entry)
+    }
+
+    // Range of the previous yank and the older kill to replace it with
+    pub fn yank_pop(&mut self) -> Option<(Range<usize>, &str)> {
+        let Last::Yank { index, range } = self.previous.clone() else {
+            return None;
+        };
+
+        let index = (index + 1) % self.entries.len();
+        let entry = &self.entries[index];
+        self.current = Last::Yank {
+            index,
+            range: range.start..range.start + entry.chars().count(),
+        };
+        Some((range, entry))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Direction, KillRing};
+
+    #[test]
+    fn consecutive_kills_concatenate() {
+        let mut kills = KillRing::default();
+
+        kills.advance();
+        kills.kill("world", Direction::Forward);
+        kills.advance();
+        kills.kill("hello ", Direction::Backward);
+        kills.advance();
+        kills.kill("!", Direction::Forward);
+
+        kills.advance();
+        assert_eq!(Some("hello world!"), kills.yank(0));
+    }
+
+    #[test]
+    fn separate_kills_push() {
+        let mut kills = KillRing::default();
+
+        kills.advance();
+        kills.kill("one", Direction::Forward);
+        kills.advance();
+        kills.advance();
+        kills.kill("two", Direction::Forward);
+
+        kills.advance();
+        assert_eq!(Some("two"), kills.yank(4));
+        kills.advance();
+        assert_eq!(Some((4..7, "one")), kills.yank_pop());
+        kills.advance();
+        assert_eq!(Some((4..7, "two")), kills.yank_pop());
+    }
+
+    #[test]
+    fn yank_pop_requires_yank() {
+        let mut kills = KillRing::default();
+
+        kills.advance();
+        kills.kill("one", Direction::Forward);
+        kills.advance();
+        assert_eq!(None, kills.yank_pop());
+    }
+}
@@ -1,21 +1,40 @@
-use pyo3::prelude::*;
-
 mod banner;
+mod completion;
+mod config;
 mod console;
+mod custom;
+mod dump;
+mod encoder;
 mod error;
+mod hex;
 mod history;
+mod inbox;
+mod keymap;
+mod killring;
 mod mode;
+mod output;
 mod path;
+#[cfg(feature = "python")]
+mod python;
+mod search;
 mod shell;
+mod terminator;
+mod undo;
+mod validator;
+mod vi;
+mod word;
 
-use console::{Action, Console};
-
+pub use completion::Completer;
+pub use config::Config;
+pub use console::{Action, Console, Printer, Waiter};
+pub use custom::Handler;
+pub use encoder::KeyEncoding;
 pub use error::{Error, Result};
-
-#[pymodule]
-fn exoshell(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    env_logger::init();
-    m.add_class::<Action>()?;
-    m.add_class::<Console>()?;
-    Ok(())
-}
+pub use keymap::Keymap;
+pub use mode::{Message, Mode, Modes};
+pub use output::{Backspaces, Controls, Escapes, Invalid, Policy, Returns};
+pub use shell::{Shell, State};
+pub use terminator::Terminator;
+pub use validator::Validator;
+pub use vi::EditMode;
+pub use word::Words;
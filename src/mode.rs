@@ -1,23 +1,57 @@
+use crate::completion::{self, Completer, Menu};
 use crate::console::Action;
-use crate::history::History;
+use crate::custom::Custom;
+use crate::encoder::{self, KeyEncoding};
+use crate::hex::Hex;
+use crate::history::{self, History};
+use crate::keymap::{Bindings, Chord, Command, Keymap};
+use crate::killring::{Direction, KillRing};
+use crate::search::Search;
 use crate::shell;
+use crate::terminator::Terminator;
+use crate::undo::{Group, Snapshot, Undo};
+use crate::validator::Validator;
+use crate::vi::{EditMode, Effect, Vi};
+use crate::word::Words;
+use crate::{Error, Result};
 use crossterm::{
     event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     style,
 };
+use std::collections::VecDeque;
+use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Mode {
     Line,
     Raw,
     Prefix,
+    Hex,
+    // Registered at runtime, by name
+    Custom(String),
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "line" => Self::Line,
+            "raw" => Self::Raw,
+            "prefix" => Self::Prefix,
+            "hex" => Self::Hex,
+            _ => Self::Custom(name.to_string()),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum Message {
     ChangeMode(Mode),
     Writeline(String),
+    Writelines(Vec<String>),
     Write(String),
+    WriteBytes(Vec<u8>),
+    ToggleEcho,
+    ToggleDump,
     Quit(),
 }
 
@@ -25,20 +59,249 @@ pub trait OnKey {
     fn on_key(&mut self, key: KeyEvent) -> Option<Message>;
 }
 
+pub trait OnPaste {
+    fn on_paste(&mut self, text: String) -> Option<Message>;
+}
+
 pub struct Line {
     contents: String,
     cursor: usize,
     history: History,
     history_index: usize,
+    search: Option<Search>,
+    completer: Option<Box<dyn Completer>>,
+    menu: Option<Menu>,
+    words: Words,
+    kills: KillRing,
+    undo: Undo,
+    validator: Option<Box<dyn Validator>>,
+    vi: Option<Vi>,
+    // Multi-line paste waiting for confirmation
+    paste: Option<String>,
+    // Line bindings, and those of the search, paste and menu sub-states
+    keymap: Keymap,
+    hints: Vec<String>,
+    search_hints: Vec<String>,
+    paste_hints: Vec<String>,
+    menu_hints: Vec<String>,
 }
 
 impl Line {
-    fn new(history: History) -> Self {
+    pub fn new(history: History) -> Self {
         Self {
             history,
             history_index: 0,
             contents: "".to_string(),
             cursor: 0,
+            search: None,
+            completer: None,
+            menu: None,
+            words: Words::default(),
+            kills: KillRing::default(),
+            undo: Undo::default(),
+            validator: None,
+            vi: None,
+            paste: None,
+            keymap: Keymap::default(),
+            hints: Vec::new(),
+            search_hints: Vec::new(),
+            paste_hints: Vec::new(),
+            menu_hints: Vec::new(),
+        }
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.hints = keymap.line.hints(&[
+            (Command::Quit, "Quit"),
+            (Command::LineSearch, "Search"),
+            (Command::ModePrefix, "Prefix"),
+        ]);
+        self.search_hints = keymap.search.hints(&[
+            (Command::SearchOlder, "Older"),
+            (Command::SearchNewer, "Newer"),
+            (Command::SearchCancel, "Cancel"),
+        ]);
+        self.paste_hints = keymap.paste.hints(&[
+            (Command::PasteSend, "Send lines"),
+            (Command::PasteEdit, "Edit"),
+            (Command::PasteCancel, "Cancel"),
+        ]);
+        self.menu_hints = keymap.menu.hints(&[
+            (Command::MenuNext, "Next"),
+            (Command::MenuPrev, "Prev"),
+            (Command::MenuClose, "Close"),
+        ]);
+        self.keymap = keymap;
+    }
+
+    // Not searching or confirming a paste, so contents are the input
+    pub fn is_editing(&self) -> bool {
+        self.paste.is_none() && self.search.is_none()
+    }
+
+    // Whether key would submit the contents as typed
+    pub fn is_submit(&self, key: KeyEvent) -> bool {
+        self.is_editing()
+            && self.menu.is_none()
+            && self.keymap.line.get(key) == Some(Command::LineSubmit)
+    }
+
+    // Replace the characters in range, leaving the cursor at the end of value
+    fn replace(&mut self, range: Range<usize>, value: &str) {
+        let mut chars = self.contents.chars();
+        let left = chars.by_ref().take(range.start).collect::<String>();
+        let right = chars.skip(range.len()).collect::<String>();
+
+        self.contents = format!("{}{}{}", left, value, right);
+        self.cursor = range.start + value.chars().count();
+        self.history_index = 0;
+    }
+
+    // Remove the characters in range, returning them
+    fn delete(&mut self, range: Range<usize>) -> String {
+        let removed = self
+            .contents
+            .chars()
+            .skip(range.start)
+            .take(range.len())
+            .collect::<String>();
+        self.replace(range, "");
+        removed
+    }
+
+    // Remove the characters in range onto the kill ring
+    fn kill(&mut self, range: Range<usize>, direction: Direction) {
+        let removed = self.delete(range);
+        self.kills.kill(&removed, direction);
+    }
+
+    fn kill_prev_word(&mut self) {
+        let start = self.words.prev(&self.contents, self.cursor);
+        self.kill(start..self.cursor, Direction::Backward);
+    }
+
+    fn kill_next_word(&mut self) {
+        let end = self.words.next(&self.contents, self.cursor);
+        self.kill(self.cursor..end, Direction::Forward);
+    }
+
+    fn yank(&mut self) {
+        if let Some(text) = self.kills.yank(self.cursor).map(str::to_string) {
+            self.replace(self.cursor..self.cursor, &text);
+        }
+    }
+
+    fn yank_pop(&mut self) {
+        if let Some((range, text)) = self
+            .kills
+            .yank_pop()
+            .map(|(range, text)| (range, text.to_string()))
+        {
+            self.replace(range, &text);
+        }
+    }
+
+    fn move_next_word(&mut self) {
+        // At end of line, take the next word of the suggestion instead
+        if !self.accept_suggestion(true) {
+            self.cursor = self.words.next(&self.contents, self.cursor);
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.validator
+            .as_ref()
+            .is_none_or(|validator| validator.is_complete(&self.contents))
+    }
+
+    // Move to the same column on the adjacent row, returning false past the first or last row
+    fn move_row(&mut self, up: bool) -> bool {
+        let chars: Vec<char> = self.contents.chars().collect();
+
+        let row_start = |index: usize| {
+            chars[..index]
+                .iter()
+                .rposition(|c| *c == '\n')
+                .map_or(0, |newline| newline + 1)
+        };
+        let row_end = |index: usize| {
+            chars[index..]
+                .iter()
+                .position(|c| *c == '\n')
+                .map_or(chars.len(), |newline| index + newline)
+        };
+
+        let start = row_start(self.cursor);
+        let column = self.cursor - start;
+
+        if up {
+            if start == 0 {
+                return false;
+            }
+            let prev_start = row_start(start - 1);
+            self.cursor = prev_start + column.min(start - 1 - prev_start);
+        } else {
+            let end = row_end(self.cursor);
+            if end == chars.len() {
+                return false;
+            }
+            let next_start = end + 1;
+            self.cursor = next_start + column.min(row_end(next_start) - next_start);
+        }
+
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            contents: self.contents.to_string(),
+            cursor: self.cursor,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.contents = snapshot.contents;
+        self.cursor = snapshot.cursor;
+        self.history_index = 0;
+    }
+
+    fn step_undo(&mut self) {
+        if let Some(snapshot) = self.undo.undo(self.snapshot()) {
+            self.restore(snapshot);
+        }
+    }
+
+    fn step_redo(&mut self) {
+        if let Some(snapshot) = self.undo.redo(self.snapshot()) {
+            self.restore(snapshot);
+        }
+    }
+
+    fn complete(&mut self) {
+        let Some(completer) = &self.completer else {
+            return;
+        };
+
+        let candidates = completer.complete(&self.contents, self.cursor);
+        let start = completion::word_start(&self.contents, self.cursor);
+        let word = self
+            .contents
+            .chars()
+            .skip(start)
+            .take(self.cursor - start)
+            .collect::<String>();
+
+        match candidates.as_slice() {
+            [] => {}
+            [candidate] => self.replace(start..self.cursor, candidate),
+            _ => {
+                // Only extend what was typed, never shorten it
+                let prefix = completion::common_prefix(&candidates);
+                if prefix.starts_with(&word) {
+                    self.replace(start..self.cursor, &prefix);
+                }
+                self.menu = Some(Menu::new(candidates, start));
+            }
         }
     }
 
@@ -61,6 +324,50 @@ impl Line {
             self.cursor = 0;
         }
     }
+
+    // Remainder of the best history entry for the current line, if the cursor is at the end
+    fn ghost(&self) -> &str {
+        if self.search.is_some()
+            || self.menu.is_some()
+            || self.paste.is_some()
+            || self.contents.is_empty()
+            || self.cursor < self.contents.chars().count()
+        {
+            return "";
+        }
+
+        let now = history::now().unwrap_or_default();
+        self.history
+            .suggest(&self.contents, now)
+            .map(|entry| &entry.cmd[self.contents.len()..])
+            .unwrap_or("")
+    }
+
+    // Returns false if there was nothing to accept
+    fn accept_suggestion(&mut self, word: bool) -> bool {
+        let ghost = self.ghost();
+
+        let accepted = match word {
+            true => {
+                // Leading whitespace, then up to the end of the next word
+                let start = ghost.len() - ghost.trim_start().len();
+                let end = ghost[start..]
+                    .find(char::is_whitespace)
+                    .map_or(ghost.len(), |end| start + end);
+                ghost[..end].to_string()
+            }
+            false => ghost.to_string(),
+        };
+
+        if accepted.is_empty() {
+            return false;
+        }
+
+        self.contents.push_str(&accepted);
+        self.cursor = self.contents.chars().count();
+        self.history_index = 0;
+        true
+    }
 }
 
 impl shell::State for Line {
@@ -69,57 +376,368 @@ impl shell::State for Line {
     }
 
     fn cursor(&self) -> usize {
-        self.cursor
+        match (&self.paste, &self.search) {
+            (Some(paste), _) => paste.chars().count(),
+            (None, Some(search)) => search.cursor(),
+            (None, None) => self.cursor,
+        }
     }
 
     fn contents(&self) -> &str {
-        &self.contents
+        match (&self.paste, &self.search) {
+            (Some(paste), _) => paste,
+            (None, Some(search)) => search.contents(),
+            (None, None) => &self.contents,
+        }
     }
 
     fn name(&self) -> &str {
-        "LINE"
+        if self.paste.is_some() {
+            return "PASTE";
+        }
+
+        match (&self.search, &self.vi) {
+            (Some(_), _) => "SEARCH",
+            (None, Some(vi)) if vi.is_insert() => "LINE:I",
+            (None, Some(_)) => "LINE:N",
+            (None, None) => "LINE",
+        }
     }
 
     fn keybinds(&self) -> Vec<&str> {
-        vec!["^D Quit", "^\\ Prefix"]
+        let hints = if self.paste.is_some() {
+            &self.paste_hints
+        } else if self.search.is_some() {
+            &self.search_hints
+        } else if self.menu.is_some() {
+            &self.menu_hints
+        } else {
+            &self.hints
+        };
+        hints.iter().map(|hint| hint.as_str()).collect()
+    }
+
+    fn highlight(&self) -> Option<Range<usize>> {
+        self.search.as_ref().and_then(|search| search.highlight())
+    }
+
+    fn suggestion(&self) -> &str {
+        self.ghost()
+    }
+
+    fn menu(&self) -> &[String] {
+        self.menu.as_ref().map_or(&[], |menu| menu.candidates())
+    }
+
+    fn menu_selected(&self) -> Option<usize> {
+        self.menu.as_ref().and_then(|menu| menu.selected())
+    }
+}
+
+impl Line {
+    fn on_search_key(&mut self, key: KeyEvent) -> Option<Message> {
+        let search = self.search.as_mut()?;
+
+        match self.keymap.search.get(key) {
+            Some(Command::SearchOlder) => search.older(),
+            Some(Command::SearchNewer) => search.newer(),
+            // Restore the original line, which was never modified
+            Some(Command::SearchCancel) => self.search = None,
+            _ => match key {
+                KeyEvent {
+                    kind: KeyEventKind::Press,
+                    modifiers: KeyModifiers::NONE,
+                    code: KeyCode::Char(c),
+                    ..
+                }
+                | KeyEvent {
+                    kind: KeyEventKind::Press,
+                    modifiers: KeyModifiers::SHIFT,
+                    code: KeyCode::Char(c),
+                    ..
+                } => search.push(c, &self.history),
+
+                KeyEvent {
+                    kind: KeyEventKind::Press,
+                    modifiers: KeyModifiers::NONE,
+                    code,
+                    ..
+                } => match code {
+                    KeyCode::Backspace => search.pop(&self.history),
+                    KeyCode::Enter => {
+                        // Accept the match into the line, leaving it for editing
+                        if let Some(cmd) = search.selected() {
+                            self.contents = cmd.to_string();
+                            self.cursor = self.contents.chars().count();
+                            self.history_index = 0;
+                        }
+                        self.search = None;
+                    }
+
+                    _ => {}
+                },
+
+                _ => {}
+            },
+        }
+
+        None
+    }
+}
+
+impl Line {
+    // Returns false if the key closed the menu and should be handled normally
+    fn on_menu_key(&mut self, key: KeyEvent) -> bool {
+        let Some(menu) = self.menu.as_mut() else {
+            return false;
+        };
+
+        let candidate = match self.keymap.menu.get(key) {
+            Some(Command::MenuNext) => menu.next().to_string(),
+            Some(Command::MenuPrev) => menu.prev().to_string(),
+
+            // Keep whatever is selected, without submitting the line
+            Some(Command::MenuClose) => {
+                self.menu = None;
+                return true;
+            }
+
+            _ => {
+                self.menu = None;
+                return false;
+            }
+        };
+
+        let start = menu.start();
+        self.replace(start..self.cursor, &candidate);
+        true
     }
 }
 
 impl OnKey for Line {
     fn on_key(&mut self, key: KeyEvent) -> Option<Message> {
-        match key {
-            KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                code: KeyCode::Home,
-                ..
+        self.kills.advance();
+
+        if self.paste.is_some() {
+            return self.on_paste_key(key);
+        }
+
+        // Undo works from any sub-state, including search
+        match self.keymap.line.get(key) {
+            Some(Command::LineUndo) => {
+                self.step_undo();
+                return None;
             }
-            | KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                code: KeyCode::Char('a'),
-                ..
-            } => {
-                self.cursor = 0;
+            Some(Command::LineRedo) => {
+                self.step_redo();
+                return None;
+            }
+            _ => {}
+        }
+
+        let normal = self.vi.as_ref().is_some_and(|vi| !vi.is_insert());
+        if normal && self.search.is_none() && self.menu.is_none() {
+            if let Some(message) = self.on_vi_key(key) {
+                return message;
+            }
+        }
+
+        let group = match (self.keymap.line.get(key), key) {
+            (Some(Command::LineHistoryPrev | Command::LineHistoryNext), _) => Some(Group::History),
+            (Some(_), _) => None,
+            (
+                None,
+                KeyEvent {
+                    kind: KeyEventKind::Press,
+                    modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                    code: KeyCode::Char(_),
+                    ..
+                },
+            ) => Some(Group::Insert),
+            (None, _) => None,
+        };
+
+        let before = self.snapshot();
+
+        let message = if self.search.is_some() {
+            self.on_search_key(key)
+        } else if self.on_menu_key(key) {
+            None
+        } else {
+            self.on_edit_key(key)
+        };
+
+        match message {
+            // Submitted lines start over with a fresh edit history
+            Some(Message::Writeline(_)) => {
+                self.undo.clear();
+                if let Some(vi) = self.vi.as_mut() {
+                    vi.reset();
+                }
+            }
+            _ => self.undo.record(before, &self.snapshot(), group),
+        }
+
+        message
+    }
+}
+
+impl OnPaste for Line {
+    fn on_paste(&mut self, text: String) -> Option<Message> {
+        if self.paste.is_some() {
+            return None;
+        }
+
+        // Terminals send CR for newlines, and tabs would render zero-width
+        let text = text
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .replace('\t', " ")
+            .chars()
+            .filter(|c| *c == '\n' || !c.is_control())
+            .collect::<String>();
+        let text = text.trim_end_matches('\n');
+
+        if let Some(search) = self.search.as_mut() {
+            for c in text.chars().filter(|c| *c != '\n') {
+                search.push(c, &self.history);
+            }
+            return None;
+        }
+
+        self.menu = None;
+
+        if text.contains('\n') {
+            self.paste = Some(text.to_string());
+        } else {
+            let before = self.snapshot();
+            self.replace(self.cursor..self.cursor, text);
+            self.undo.record(before, &self.snapshot(), None);
+        }
+
+        None
+    }
+}
+
+impl Line {
+    fn on_paste_key(&mut self, key: KeyEvent) -> Option<Message> {
+        match self.keymap.paste.get(key) {
+            Some(Command::PasteSend) => {
+                let lines: Vec<String> = self.paste.take()?.split('\n').map(String::from).collect();
+
+                for line in lines.iter().filter(|line| !line.is_empty()) {
+                    if let Err(err) = self.history.update(line) {
+                        log::warn!("could not update history: {:?}", err)
+                    }
+                }
+
+                Some(Message::Writelines(lines))
+            }
+
+            // Insert into the line as a single edit instead
+            Some(Command::PasteEdit) => {
+                let paste = self.paste.take()?;
+                let before = self.snapshot();
+                self.replace(self.cursor..self.cursor, &paste);
+                self.undo.record(before, &self.snapshot(), None);
+                None
+            }
+
+            Some(Command::PasteCancel) => {
+                self.paste = None;
                 None
             }
 
+            _ => None,
+        }
+    }
+
+    // Vi normal mode, returning None for keys that fall through to the usual bindings
+    fn on_vi_key(&mut self, key: KeyEvent) -> Option<Option<Message>> {
+        let c = match key {
+            KeyEvent {
+                kind: KeyEventKind::Press,
+                modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
+                code: KeyCode::Char(c),
+                ..
+            } => c,
+
             KeyEvent {
                 kind: KeyEventKind::Press,
                 modifiers: KeyModifiers::NONE,
-                code: KeyCode::End,
+                code,
                 ..
-            }
-            | KeyEvent {
+            } => match code {
+                KeyCode::Left | KeyCode::Backspace => 'h',
+                KeyCode::Right => 'l',
+                KeyCode::Up => 'k',
+                KeyCode::Down => 'j',
+                KeyCode::Home => '0',
+                KeyCode::End => '$',
+                KeyCode::Esc => {
+                    let vi = self.vi.as_mut()?;
+                    vi.escape(&self.contents, &mut self.cursor);
+                    return Some(None);
+                }
+                _ => return None,
+            },
+
+            KeyEvent {
                 kind: KeyEventKind::Press,
                 modifiers: KeyModifiers::CONTROL,
-                code: KeyCode::Char('e'),
+                code: KeyCode::Char('r'),
                 ..
             } => {
-                self.cursor = self.contents.chars().count();
-                None
+                self.step_redo();
+                return Some(None);
+            }
+
+            _ => return None,
+        };
+
+        let before = self.snapshot();
+        let vi = self.vi.as_mut()?;
+
+        let group = match vi.on_char(c, &mut self.contents, &mut self.cursor) {
+            Effect::None => None,
+            Effect::Undo(count) => {
+                for _ in 0..count {
+                    self.step_undo();
+                }
+                // Undo restores insert positions, which may be past the end in normal mode
+                self.cursor = self
+                    .cursor
+                    .min(self.contents.chars().count().saturating_sub(1));
+                return Some(None);
+            }
+            Effect::Older(count) => {
+                self.select_history(count, 0);
+                Some(Group::History)
             }
+            Effect::Newer(count) => {
+                self.select_history(0, count);
+                Some(Group::History)
+            }
+        };
 
+        if self.vi.as_ref().is_some_and(|vi| !vi.is_insert()) {
+            self.cursor = self
+                .cursor
+                .min(self.contents.chars().count().saturating_sub(1));
+        }
+
+        self.undo.record(before, &self.snapshot(), group);
+        Some(None)
+    }
+}
+
+impl Line {
+    fn on_edit_key(&mut self, key: KeyEvent) -> Option<Message> {
+        if let Some(command) = self.keymap.line.get(key) {
+            return self.on_command(command);
+        }
+
+        match key {
             KeyEvent {
                 kind: KeyEventKind::Press,
                 modifiers: KeyModifiers::NONE,
@@ -132,7 +750,7 @@ impl OnKey for Line {
                 code: KeyCode::Char(c),
                 ..
             } => {
-                let mut chars = self.contents.chars().into_iter();
+                let mut chars = self.contents.chars();
                 self.contents = format!(
                     "{}{}{}",
                     chars.by_ref().take(self.cursor).collect::<String>(),
@@ -145,102 +763,204 @@ impl OnKey for Line {
                 None
             }
 
-            KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                code,
-                ..
-            } => match code {
-                KeyCode::Enter => {
-                    let cmd = self.contents.to_string();
+            _ => None,
+        }
+    }
 
-                    // Only update history if cmd isn't empty
-                    if cmd.len() > 0 {
-                        if let Err(err) = self.history.update(&cmd) {
-                            log::warn!("could not update history: {:?}", err)
-                        }
-                    }
+    fn on_command(&mut self, command: Command) -> Option<Message> {
+        match command {
+            Command::LineBeginning => {
+                self.cursor = 0;
+                None
+            }
+            Command::LineEnd => {
+                // At end of line, accept the suggestion instead
+                if self.cursor < self.contents.chars().count() || !self.accept_suggestion(false) {
+                    self.cursor = self.contents.chars().count();
+                }
+                None
+            }
 
-                    self.contents = "".to_string();
-                    self.cursor = 0;
-                    self.history_index = 0;
+            Command::LineSubmit if !self.is_complete() => {
+                self.replace(self.cursor..self.cursor, "\n");
+                None
+            }
+            Command::LineSubmit => {
+                let cmd = self.contents.to_string();
 
-                    Some(Message::Writeline(cmd))
+                // Only update history if cmd isn't empty
+                if !cmd.is_empty() {
+                    if let Err(err) = self.history.update(&cmd) {
+                        log::warn!("could not update history: {:?}", err)
+                    }
                 }
 
-                KeyCode::Left => {
-                    self.cursor = self.cursor.saturating_sub(1);
-                    None
+                self.contents = "".to_string();
+                self.cursor = 0;
+                self.history_index = 0;
+
+                Some(Message::Writeline(cmd))
+            }
+            // Always start a new row, regardless of the validator
+            Command::LineNewline => {
+                self.replace(self.cursor..self.cursor, "\n");
+                None
+            }
+            Command::LineEscape => {
+                if let Some(vi) = self.vi.as_mut() {
+                    vi.escape(&self.contents, &mut self.cursor);
                 }
-                KeyCode::Right => {
-                    // For right, we need to clamp by the length of the current contents
+                None
+            }
+
+            Command::LineBackwardChar => {
+                self.cursor = self.cursor.saturating_sub(1);
+                None
+            }
+            Command::LineForwardChar => {
+                // For right, we need to clamp by the length of the current contents
+                if !self.accept_suggestion(false) {
                     self.cursor = (self.cursor + 1).min(self.contents.chars().count());
-                    None
                 }
-                KeyCode::Up => {
+                None
+            }
+            Command::LineBackwardWord => {
+                self.cursor = self.words.prev(&self.contents, self.cursor);
+                None
+            }
+            Command::LineForwardWord => {
+                self.move_next_word();
+                None
+            }
+
+            Command::LineHistoryPrev => {
+                if !self.move_row(true) {
                     self.select_history(1, 0);
-                    None
                 }
-                KeyCode::Down => {
+                None
+            }
+            Command::LineHistoryNext => {
+                if !self.move_row(false) {
                     self.select_history(0, 1);
-                    None
                 }
+                None
+            }
+            Command::LineComplete => {
+                self.complete();
+                None
+            }
 
-                KeyCode::Backspace => {
-                    if self.cursor > 0 {
-                        self.cursor -= 1;
-                        let mut chars = self.contents.chars();
-
-                        let left = chars.by_ref().take(self.cursor).collect::<String>();
-                        chars.by_ref().next();
-                        let right = chars.by_ref().collect::<String>();
-
-                        self.contents = format!("{}{}", left, right);
-                    }
-                    None
-                }
-                KeyCode::Delete => {
-                    if self.cursor < self.contents.chars().count() {
-                        let mut chars = self.contents.chars();
+            Command::LineDeleteBackward => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    let mut chars = self.contents.chars();
 
-                        let left = chars.by_ref().take(self.cursor).collect::<String>();
-                        chars.by_ref().next();
-                        let right = chars.by_ref().collect::<String>();
+                    let left = chars.by_ref().take(self.cursor).collect::<String>();
+                    chars.by_ref().next();
+                    let right = chars.by_ref().collect::<String>();
 
-                        self.contents = format!("{}{}", left, right);
-                    }
-                    None
+                    self.contents = format!("{}{}", left, right);
                 }
+                None
+            }
+            Command::LineDeleteForward => {
+                if self.cursor < self.contents.chars().count() {
+                    let mut chars = self.contents.chars();
 
-                // TODO: history with up/down arrows
-                _ => None,
-            },
+                    let left = chars.by_ref().take(self.cursor).collect::<String>();
+                    chars.by_ref().next();
+                    let right = chars.by_ref().collect::<String>();
 
-            KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                code,
-                ..
-            } => match code {
-                KeyCode::Char('d') => Some(Message::Quit()),
-                KeyCode::Char('c') => {
-                    self.contents = "".to_string();
-                    self.cursor = 0;
-                    None
+                    self.contents = format!("{}{}", left, right);
                 }
-                KeyCode::Char('4') | KeyCode::Char('\\') => Some(Message::ChangeMode(Mode::Prefix)),
+                None
+            }
+            Command::LineKillWordBackward => {
+                self.kill_prev_word();
+                None
+            }
+            Command::LineKillWordForward => {
+                self.kill_next_word();
+                None
+            }
+            Command::LineKillStart => {
+                self.kill(0..self.cursor, Direction::Backward);
+                None
+            }
+            Command::LineKillEnd => {
+                self.kill(
+                    self.cursor..self.contents.chars().count(),
+                    Direction::Forward,
+                );
+                None
+            }
+            Command::LineYank => {
+                self.yank();
+                None
+            }
+            Command::LineYankPop => {
+                self.yank_pop();
+                None
+            }
 
-                // TODO: C-Backspace / C-Left / C-Right
-                _ => None,
-            },
+            Command::LineUndo => {
+                self.step_undo();
+                None
+            }
+            Command::LineRedo => {
+                self.step_redo();
+                None
+            }
+            Command::LineClear => {
+                self.contents = "".to_string();
+                self.cursor = 0;
+                None
+            }
+            Command::LineSearch => {
+                self.search = Some(Search::new());
+                None
+            }
 
-            _ => None,
+            command => on_mode_command(command, Mode::Line),
         }
     }
 }
 
+// Commands shared by every mode; `back` is where mode.return leads
+pub fn on_mode_command(command: Command, back: Mode) -> Option<Message> {
+    match command {
+        Command::Quit => Some(Message::Quit()),
+        Command::ModePrefix => Some(Message::ChangeMode(Mode::Prefix)),
+        Command::ModeLine => Some(Message::ChangeMode(Mode::Line)),
+        Command::ModeRaw => Some(Message::ChangeMode(Mode::Raw)),
+        Command::ModeHex => Some(Message::ChangeMode(Mode::Hex)),
+        Command::ModeReturn => Some(Message::ChangeMode(back)),
+        Command::RawEcho => Some(Message::ToggleEcho),
+        Command::OutputDump => Some(Message::ToggleDump),
+        _ => None,
+    }
+}
+
 #[derive(Default)]
-pub struct Raw {}
+pub struct Raw {
+    bindings: Bindings,
+    hints: Vec<String>,
+    // Sent for Enter
+    terminator: Terminator,
+    encoding: KeyEncoding,
+    // Show what is sent, for devices that don't echo
+    echo: bool,
+}
+
+impl Raw {
+    fn set_bindings(&mut self, bindings: Bindings) {
+        self.hints = bindings
+            .hint(Command::ModePrefix, "Prefix")
+            .into_iter()
+            .collect();
+        self.bindings = bindings;
+    }
+}
 
 impl shell::State for Raw {
     fn color(&self) -> style::Color {
@@ -260,87 +980,87 @@ impl shell::State for Raw {
     }
 
     fn keybinds(&self) -> Vec<&str> {
-        vec!["^\\ Prefix"]
+        self.hints.iter().map(|hint| hint.as_str()).collect()
+    }
+}
+
+impl OnPaste for Raw {
+    fn on_paste(&mut self, text: String) -> Option<Message> {
+        Some(Message::Write(text))
     }
 }
 
 impl OnKey for Raw {
     fn on_key(&mut self, key: KeyEvent) -> Option<Message> {
-        match key {
-            KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                code: KeyCode::Char(c),
-                ..
-            }
-            | KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::SHIFT,
-                code: KeyCode::Char(c),
-                ..
-            } => Some(Message::Write(c.into())),
-
-            KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                code,
-                ..
-            } => match code {
-                KeyCode::Enter => Some(Message::Write("\n".into())),
-                KeyCode::Backspace => Some(Message::Write("\u{7f}".into())),
-                KeyCode::Esc => Some(Message::Write("\u{1b}".into())),
-                KeyCode::Tab => Some(Message::Write("\u{09}".into())),
-                KeyCode::Up => Some(Message::Write("\u{1b}[A".into())),
-                KeyCode::Down => Some(Message::Write("\u{1b}[B".into())),
-
-                _ => None,
-            },
+        if let Some(command) = self.bindings.get(key) {
+            return on_mode_command(command, Mode::Raw);
+        }
 
+        match key {
             KeyEvent {
                 kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                code,
+                code: KeyCode::Enter,
                 ..
-            } => match code {
-                KeyCode::Char('4') | KeyCode::Char('\\') => Some(Message::ChangeMode(Mode::Prefix)),
-
-                KeyCode::Char('a') => Some(Message::Write("\u{1}".into())), // SOH
-                KeyCode::Char('b') => Some(Message::Write("\u{2}".into())), // STX
-                KeyCode::Char('c') => Some(Message::Write("\u{3}".into())), // ETX
-                KeyCode::Char('d') => Some(Message::Write("\u{4}".into())), // EOT
-                KeyCode::Char('e') => Some(Message::Write("\u{5}".into())), // ENQ
-                KeyCode::Char('f') => Some(Message::Write("\u{6}".into())), // EOT
-                KeyCode::Char('g') => Some(Message::Write("\u{7}".into())), // EOT
-                KeyCode::Char('h') => Some(Message::Write("\u{8}".into())), // BS
-                KeyCode::Char('i') => Some(Message::Write("\u{9}".into())), // HT
-                KeyCode::Char('j') => Some(Message::Write("\u{a}".into())), // LF
-                KeyCode::Char('k') => Some(Message::Write("\u{b}".into())), // VT
-                KeyCode::Char('l') => Some(Message::Write("\u{c}".into())), // FF
-                KeyCode::Char('m') => Some(Message::Write("\u{d}".into())), // CR
-                KeyCode::Char('n') => Some(Message::Write("\u{e}".into())), // SO
-                KeyCode::Char('o') => Some(Message::Write("\u{f}".into())), // SI
-                KeyCode::Char('p') => Some(Message::Write("\u{10}".into())), // DLE
-                KeyCode::Char('q') => Some(Message::Write("\u{11}".into())), // DC1
-                KeyCode::Char('r') => Some(Message::Write("\u{12}".into())), // DC2
-                KeyCode::Char('s') => Some(Message::Write("\u{13}".into())), // DC3
-                KeyCode::Char('t') => Some(Message::Write("\u{14}".into())), // DC4
-                KeyCode::Char('u') => Some(Message::Write("\u{15}".into())), // NAK
-                KeyCode::Char('v') => Some(Message::Write("\u{16}".into())), // SYN
-                KeyCode::Char('w') => Some(Message::Write("\u{17}".into())), // ETB
-                KeyCode::Char('x') => Some(Message::Write("\u{18}".into())), // CAN
-                KeyCode::Char('y') => Some(Message::Write("\u{19}".into())), // EM
-                KeyCode::Char('z') => Some(Message::Write("\u{1a}".into())), // SUB
-
-                _ => None,
+            } => match self.terminator {
+                Terminator::None => None,
+                terminator => Some(Message::Write(terminator.as_str().into())),
             },
-
-            _ => None,
+            _ => encoder::encode(key, self.encoding).map(Message::Write),
         }
     }
 }
 
 #[derive(Default)]
-pub struct Prefix {}
+pub struct Prefix {
+    bindings: Bindings,
+    // Keys selecting custom modes
+    custom: Vec<(char, String)>,
+    hints: Vec<String>,
+}
+
+impl Prefix {
+    fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+        self.update_hints();
+    }
+
+    fn set_custom(&mut self, custom: &[Custom]) {
+        self.custom = custom
+            .iter()
+            .map(|mode| (mode.key(), mode.name().to_string()))
+            .collect();
+        self.update_hints();
+    }
+
+    fn update_hints(&mut self) {
+        let hint = |command, label| self.bindings.hint(command, label);
+
+        let mut hints: Vec<_> = [
+            hint(Command::Quit, "Quit"),
+            hint(Command::ModeRaw, "Raw"),
+            hint(Command::ModeLine, "Line"),
+            hint(Command::ModeHex, "Hex"),
+            hint(Command::RawEcho, "Echo"),
+            hint(Command::OutputDump, "Dump"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        hints.extend(
+            self.custom
+                .iter()
+                .map(|(key, name)| format!("{} {}", key, name)),
+        );
+        hints.extend(hint(Command::ModeReturn, "Return"));
+
+        self.hints = hints;
+    }
+
+    fn is_bound(&self, key: char) -> bool {
+        let chord = Chord::new(KeyCode::Char(key), KeyModifiers::NONE);
+        self.bindings.get_chord(chord).is_some()
+    }
+}
 
 impl shell::State for Prefix {
     fn color(&self) -> style::Color {
@@ -360,36 +1080,33 @@ impl shell::State for Prefix {
     }
 
     fn keybinds(&self) -> Vec<&str> {
-        vec!["q Quit", "r Raw", "l Line", "^\\ Return"]
+        self.hints.iter().map(|hint| hint.as_str()).collect()
+    }
+}
+
+impl OnPaste for Prefix {
+    fn on_paste(&mut self, _text: String) -> Option<Message> {
+        None
     }
 }
 
 impl OnKey for Prefix {
     fn on_key(&mut self, key: KeyEvent) -> Option<Message> {
+        if let Some(command) = self.bindings.get(key) {
+            return on_mode_command(command, Mode::Line);
+        }
+
         match key {
             KeyEvent {
                 kind: KeyEventKind::Press,
                 modifiers: KeyModifiers::NONE,
-                code,
-                ..
-            } => match code {
-                KeyCode::Char('q') => Some(Message::Quit()),
-                KeyCode::Char('l') => Some(Message::ChangeMode(Mode::Line)),
-                KeyCode::Char('r') => Some(Message::ChangeMode(Mode::Raw)),
-
-                _ => None,
-            },
-
-            KeyEvent {
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                code,
+                code: KeyCode::Char(c),
                 ..
-            } => match code {
-                KeyCode::Char('4') | KeyCode::Char('\\') => Some(Message::ChangeMode(Mode::Line)),
-
-                _ => None,
-            },
+            } => self
+                .custom
+                .iter()
+                .find(|(key, _)| *key == c)
+                .map(|(_, name)| Message::ChangeMode(Mode::Custom(name.to_string()))),
 
             _ => None,
         }
@@ -400,34 +1117,198 @@ pub struct Modes {
     line: Line,
     prefix: Prefix,
     raw: Raw,
+    hex: Hex,
+    custom: Vec<Custom>,
+    keymap: Keymap,
     mode: Mode,
+    // Show output as a hex dump
+    dump: bool,
+    // Actions waiting to be returned, one per update
+    queue: VecDeque<Action>,
 }
 
 impl Modes {
-    pub fn new(history: History) -> Self {
-        Self {
+    // Hex mode keeps its own history, so byte strings don't mix with text
+    pub fn new(history: History, hex_history: History) -> Self {
+        let mut modes = Self {
             line: Line::new(history),
             prefix: Prefix::default(),
             raw: Raw::default(),
+            hex: Hex::new(hex_history),
+            custom: Vec::new(),
+            keymap: Keymap::default(),
             mode: Mode::Line,
+            dump: false,
+            queue: VecDeque::new(),
+        };
+        modes.set_keymap(Keymap::default());
+        modes
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.line.set_keymap(keymap.clone());
+        self.hex.set_keymap(keymap.clone());
+        self.raw.set_bindings(keymap.raw.clone());
+        self.prefix.set_bindings(keymap.prefix.clone());
+        for custom in self.custom.iter_mut() {
+            custom.set_bindings(keymap.custom.clone());
+        }
+        self.keymap = keymap;
+    }
+
+    pub fn set_terminator(&mut self, terminator: Terminator) {
+        self.raw.terminator = terminator;
+    }
+
+    pub fn set_key_encoding(&mut self, encoding: KeyEncoding) {
+        self.raw.encoding = encoding;
+    }
+
+    pub fn set_echo(&mut self, echo: bool) {
+        self.raw.echo = echo;
+    }
+
+    pub fn echo(&self) -> bool {
+        self.raw.echo
+    }
+
+    pub fn set_dump(&mut self, dump: bool) {
+        self.dump = dump;
+    }
+
+    pub fn dump(&self) -> bool {
+        self.dump
+    }
+
+    // Whether what is sent now should be echoed
+    pub fn echoing(&self) -> bool {
+        self.mode == Mode::Raw && self.raw.echo
+    }
+
+    pub fn set_completer(&mut self, completer: Option<Box<dyn Completer>>) {
+        self.line.completer = completer;
+        self.line.menu = None;
+    }
+
+    pub fn set_validator(&mut self, validator: Option<Box<dyn Validator>>) {
+        self.line.validator = validator;
+    }
+
+    pub fn set_edit_mode(&mut self, mode: EditMode) {
+        for line in [&mut self.line, self.hex.line_mut()] {
+            line.vi = match mode {
+                EditMode::Emacs => None,
+                EditMode::Vi => Some(Vi::new()),
+            };
+        }
+    }
+
+    pub fn set_words(&mut self, words: Words) {
+        self.line.words = words;
+        self.hex.line_mut().words = words;
+    }
+
+    // Replaces any custom mode with the same name
+    pub fn add_custom(&mut self, mut custom: Custom) -> Result<()> {
+        if matches!(custom.name(), "line" | "raw" | "prefix" | "hex") {
+            return Err(Error::Value(format!(
+                "mode name {:?} is reserved",
+                custom.name()
+            )));
+        }
+
+        let reserved = self.prefix.is_bound(custom.key());
+        let taken = self
+            .custom
+            .iter()
+            .any(|mode| mode.key() == custom.key() && mode.name() != custom.name());
+        if reserved || taken {
+            return Err(Error::Value(format!(
+                "prefix key {:?} is already bound",
+                custom.key()
+            )));
+        }
+
+        custom.set_bindings(self.keymap.custom.clone());
+        self.custom.retain(|mode| mode.name() != custom.name());
+        self.custom.push(custom);
+        self.prefix.set_custom(&self.custom);
+        Ok(())
+    }
+
+    fn custom_mut(&mut self, name: &str) -> Option<&mut Custom> {
+        self.custom.iter_mut().find(|mode| mode.name() == name)
+    }
+
+    fn active(&self) -> &dyn shell::State {
+        match &self.mode {
+            Mode::Line => &self.line,
+            Mode::Raw => &self.raw,
+            Mode::Hex => &self.hex,
+            Mode::Prefix => &self.prefix,
+            Mode::Custom(name) => match self.custom.iter().find(|mode| mode.name() == name) {
+                Some(custom) => custom,
+                None => &self.line,
+            },
         }
     }
 
     pub fn on_key(&mut self, key: KeyEvent) -> Option<Action> {
-        let message = match self.mode {
+        let message = match self.mode.clone() {
             Mode::Line => self.line.on_key(key),
             Mode::Raw => self.raw.on_key(key),
+            Mode::Hex => self.hex.on_key(key),
             Mode::Prefix => self.prefix.on_key(key),
+            Mode::Custom(name) => self.custom_mut(&name)?.on_key(key),
         };
 
+        self.on_message(message)
+    }
+
+    pub fn on_paste(&mut self, text: String) -> Option<Action> {
+        let message = match self.mode.clone() {
+            Mode::Line => self.line.on_paste(text),
+            Mode::Raw => self.raw.on_paste(text),
+            Mode::Hex => self.hex.on_paste(text),
+            Mode::Prefix => self.prefix.on_paste(text),
+            Mode::Custom(name) => self.custom_mut(&name)?.on_paste(text),
+        };
+
+        self.on_message(message)
+    }
+
+    pub fn next_queued(&mut self) -> Option<Action> {
+        self.queue.pop_front()
+    }
+
+    fn on_message(&mut self, message: Option<Message>) -> Option<Action> {
         match message {
             Some(message) => match message {
+                Message::ChangeMode(Mode::Custom(name))
+                    if !self.custom.iter().any(|mode| mode.name() == name) =>
+                {
+                    log::warn!("no such mode: {}", name);
+                    None
+                }
                 Message::ChangeMode(mode) => {
                     self.mode = mode;
                     None
                 }
                 Message::Writeline(line) => Some(Action::Writeline(line)),
-                Message::Write(bytes) => Some(Action::Write(bytes)),
+                Message::Writelines(lines) => {
+                    self.queue.extend(lines.into_iter().map(Action::Writeline));
+                    self.queue.pop_front()
+                }
+                Message::Write(text) => Some(Action::Write(text)),
+                Message::WriteBytes(bytes) => Some(Action::WriteBytes(bytes)),
+                Message::ToggleEcho => {
+                    self.raw.echo = !self.raw.echo;
+                    None
+                }
+                Message::ToggleDump => {
+                    self.dump = !self.dump;
+                    None
+                }
                 Message::Quit() => Some(Action::Quit()),
             },
             None => None,
@@ -437,42 +1318,162 @@ impl Modes {
 
 impl shell::State for Modes {
     fn color(&self) -> style::Color {
-        match self.mode {
-            Mode::Line => self.line.color(),
-            Mode::Raw => self.raw.color(),
-            Mode::Prefix => self.prefix.color(),
-        }
+        self.active().color()
     }
 
     fn cursor(&self) -> usize {
-        match self.mode {
-            Mode::Line => self.line.cursor(),
-            Mode::Raw => self.raw.cursor(),
-            Mode::Prefix => self.prefix.cursor(),
-        }
+        self.active().cursor()
     }
 
     fn contents(&self) -> &str {
-        match self.mode {
-            Mode::Line => self.line.contents(),
-            Mode::Raw => self.raw.contents(),
-            Mode::Prefix => self.prefix.contents(),
-        }
+        self.active().contents()
     }
 
     fn name(&self) -> &str {
-        match self.mode {
-            Mode::Line => self.line.name(),
-            Mode::Raw => self.raw.name(),
-            Mode::Prefix => self.prefix.name(),
-        }
+        self.active().name()
     }
 
     fn keybinds(&self) -> Vec<&str> {
-        match self.mode {
-            Mode::Line => self.line.keybinds(),
-            Mode::Raw => self.raw.keybinds(),
-            Mode::Prefix => self.prefix.keybinds(),
+        self.active().keybinds()
+    }
+
+    fn highlight(&self) -> Option<Range<usize>> {
+        self.active().highlight()
+    }
+
+    fn suggestion(&self) -> &str {
+        self.active().suggestion()
+    }
+
+    fn menu(&self) -> &[String] {
+        self.active().menu()
+    }
+
+    fn menu_selected(&self) -> Option<usize> {
+        self.active().menu_selected()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Line, Modes, OnKey, OnPaste};
+    use crate::console::Action;
+    use crate::history::History;
+    use crate::validator::Validator;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    // Complete once every ( is closed
+    struct Balanced;
+
+    impl Validator for Balanced {
+        fn is_complete(&self, input: &str) -> bool {
+            input.matches('(').count() <= input.matches(')').count()
+        }
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_text(modes: &mut Modes, text: &str) {
+        for c in text.chars() {
+            modes.on_key(key(KeyCode::Char(c)));
         }
     }
+
+    #[test]
+    fn submit_waits_for_complete_input() {
+        let mut modes = Modes::new(History::default(), History::default());
+        modes.set_validator(Some(Box::new(Balanced)));
+
+        type_text(&mut modes, "(a");
+        assert_eq!(None, modes.on_key(key(KeyCode::Enter)));
+        assert_eq!(
+            ("(a\n", 3),
+            (modes.line.contents.as_str(), modes.line.cursor)
+        );
+
+        type_text(&mut modes, "b)");
+        assert_eq!(
+            Some(Action::Writeline("(a\nb)".into())),
+            modes.on_key(key(KeyCode::Enter))
+        );
+        assert_eq!("", modes.line.contents);
+    }
+
+    #[test]
+    fn rows_keep_column_where_they_can() {
+        let mut line = Line::new(History::default());
+        line.contents = "abcdef\nab\nabcd".into();
+        line.cursor = 14;
+
+        // Clamped to the end of the shorter row
+        line.on_key(key(KeyCode::Up));
+        assert_eq!(9, line.cursor);
+        line.on_key(key(KeyCode::Up));
+        assert_eq!(2, line.cursor);
+        line.on_key(key(KeyCode::Down));
+        assert_eq!(9, line.cursor);
+        line.on_key(key(KeyCode::Down));
+        assert_eq!(12, line.cursor);
+        assert_eq!("abcdef\nab\nabcd", line.contents);
+    }
+
+    #[test]
+    fn rows_fall_back_to_history() {
+        let mut history = History::default();
+        history.add("older", 1);
+        history.add("newer", 2);
+        let mut line = Line::new(history);
+        line.contents = "a\nb".into();
+        line.cursor = 3;
+
+        line.on_key(key(KeyCode::Up));
+        assert_eq!(("a\nb", 1), (line.contents.as_str(), line.cursor));
+
+        // Past the first row
+        line.on_key(key(KeyCode::Up));
+        assert_eq!("newer", line.contents);
+        line.on_key(key(KeyCode::Up));
+        assert_eq!("older", line.contents);
+
+        // Past the last row
+        line.on_key(key(KeyCode::Down));
+        assert_eq!("newer", line.contents);
+    }
+
+    #[test]
+    fn paste_normalizes_line_endings() {
+        let mut line = Line::new(History::default());
+        line.on_paste("a\r\nb\rc\td\x07\n\n".into());
+        assert_eq!(Some("a\nb\nc d"), line.paste.as_deref());
+        assert_eq!("", line.contents);
+    }
+
+    #[test]
+    fn paste_single_line_is_one_edit() {
+        let mut line = Line::new(History::default());
+        line.on_key(key(KeyCode::Char('x')));
+
+        // The trailing newline doesn't make it a multi-line paste
+        line.on_paste("abc\n".into());
+        assert_eq!(None, line.paste);
+        assert_eq!(("xabc", 4), (line.contents.as_str(), line.cursor));
+
+        line.on_key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        assert_eq!(("x", 1), (line.contents.as_str(), line.cursor));
+    }
+
+    #[test]
+    fn paste_sends_one_line_at_a_time() {
+        let mut modes = Modes::new(History::default(), History::default());
+        assert_eq!(None, modes.on_paste("one\ntwo\n".into()));
+
+        assert_eq!(
+            Some(Action::Writeline("one".into())),
+            modes.on_key(key(KeyCode::Char('y')))
+        );
+        assert_eq!(Some(Action::Writeline("two".into())), modes.next_queued());
+        assert_eq!(None, modes.next_queued());
+    }
 }
@@ -0,0 +1,480 @@
+use serde::Deserialize;
+use std::mem;
+use std::str;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+// Longest escape sequence kept before it is treated as stray text
+const MAX_SEQUENCE: usize = 256;
+
+// What a carriage return not followed by a line feed does
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Returns {
+    // Starts a new line, for devices ending lines with \r
+    #[default]
+    Newline,
+    // Returns to the start of the line, for progress bars
+    Keep,
+    Strip,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backspaces {
+    // Moves the cursor back, as a terminal would
+    #[default]
+    Interpret,
+    Strip,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Escapes {
+    Pass,
+    // Pass colors and styles only, so cursor movement can't break the shell box
+    #[default]
+    Color,
+    Strip,
+}
+
+// How other control characters are shown
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Controls {
+    // e.g. ^G
+    #[default]
+    Caret,
+    // e.g. <0x07>
+    Hex,
+}
+
+impl FromStr for Returns {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "newline" => Ok(Self::Newline),
+            "keep" => Ok(Self::Keep),
+            "strip" => Ok(Self::Strip),
+            _ => Err(Error::Value(format!(
+                "unknown returns policy {:?}, expected \"newline\", \"keep\" or \"strip\"",
+                value
+            ))),
+        }
+    }
+}
+
+impl FromStr for Backspaces {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "interpret" => Ok(Self::Interpret),
+            "strip" => Ok(Self::Strip),
+            _ => Err(Error::Value(format!(
+                "unknown backspaces policy {:?}, expected \"interpret\" or \"strip\"",
+                value
+            ))),
+        }
+    }
+}
+
+impl FromStr for Escapes {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "pass" => Ok(Self::Pass),
+            "color" => Ok(Self::Color),
+            "strip" => Ok(Self::Strip),
+            _ => Err(Error::Value(format!(
+                "unknown escapes policy {:?}, expected \"pass\", \"color\" or \"strip\"",
+                value
+            ))),
+        }
+    }
+}
+
+impl FromStr for Controls {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "caret" => Ok(Self::Caret),
+            "hex" => Ok(Self::Hex),
+            _ => Err(Error::Value(format!(
+                "unknown controls policy {:?}, expected \"caret\" or \"hex\"",
+                value
+            ))),
+        }
+    }
+}
+
+// How bytes that aren't valid UTF-8 are shown
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Invalid {
+    // e.g. <0xff>
+    #[default]
+    Hex,
+    // One replacement character per invalid sequence
+    Replace,
+}
+
+impl FromStr for Invalid {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "hex" => Ok(Self::Hex),
+            "replace" => Ok(Self::Replace),
+            _ => Err(Error::Value(format!(
+                "unknown invalid policy {:?}, expected \"hex\" or \"replace\"",
+                value
+            ))),
+        }
+    }
+}
+
+// How printed output is cleaned up, e.g. `(returns: keep, escapes: strip)` in config.ron
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Policy {
+    pub returns: Returns,
+    pub backspaces: Backspaces,
+    pub escapes: Escapes,
+    pub controls: Controls,
+    pub invalid: Invalid,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum State {
+    #[default]
+    Text,
+    // After ESC
+    Escape,
+    // After ESC [
+    Csi,
+    // After ESC ], or the DCS, SOS, PM and APC introducers ESC P, ESC X, ESC ^ and ESC _,
+    // until BEL or ESC \
+    Osc,
+    OscEscape,
+}
+
+// Turns output into text that only moves the cursor with \n, \r and \b.
+// Keeps state between calls, so sequences split across prints are handled.
+#[derive(Default)]
+pub struct Normalizer {
+    policy: Policy,
+    state: State,
+    // Escape sequence collected so far
+    sequence: String,
+    // Last character was \r
+    returned: bool,
+    // Start of a UTF-8 sequence split across calls
+    partial: Vec<u8>,
+}
+
+impl Normalizer {
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = policy;
+    }
+
+    pub fn normalize_bytes(&mut self, input: &[u8]) -> String {
+        let mut bytes = mem::take(&mut self.partial);
+        bytes.extend_from_slice(input);
+
+        let mut output = String::with_capacity(bytes.len());
+        let mut rest = &bytes[..];
+        loop {
+            let (valid, invalid) = match str::from_utf8(rest) {
+                Ok(valid) => (valid, None),
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    // Only valid UTF-8 is before valid_up_to
+                    let valid = str::from_utf8(valid).unwrap_or_default();
+                    (valid, Some((invalid, err.error_len())))
+                }
+            };
+            for c in valid.chars() {
+                self.push(c, &mut output);
+            }
+
+            match invalid {
+                None => return output,
+                Some((invalid, Some(len))) => {
+                    self.push_invalid(&invalid[..len], &mut output);
+                    rest = &invalid[len..];
+                }
+                // Incomplete sequence at the end, wait for the rest
+                Some((invalid, None)) => {
+                    self.partial = invalid.to_vec();
+                    return output;
+                }
+            }
+        }
+    }
+
+    // Text with every control character shown, except line endings, e.g. for local echo
+    pub fn visible(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    chars.next_if_eq(&'\n');
+                    output.push('\n');
+                }
+                '\n' | '\t' => output.push(c),
+                _ if c.is_control() => self.push_control(c, &mut output),
+                _ => output.push(c),
+            }
+        }
+        output
+    }
+
+    fn push_invalid(&mut self, bytes: &[u8], output: &mut String) {
+        match self.policy.invalid {
+            Invalid::Hex => {
+                // Ends any escape sequence, like other text would
+                if self.state != State::Text {
+                    let sequence = mem::take(&mut self.sequence);
+                    self.state = State::Text;
+                    self.push_stray(&sequence, output);
+                }
+                if mem::take(&mut self.returned) && self.policy.returns == Returns::Keep {
+                    output.push('\r');
+                }
+                for byte in bytes {
+                    output.push_str(&format!("<0x{:02x}>", byte));
+                }
+            }
+            Invalid::Replace => self.push(char::REPLACEMENT_CHARACTER, output),
+        }
+    }
+
+    fn push(&mut self, c: char, output: &mut String) {
+        match self.state {
+            State::Text => self.push_text(c, output),
+            State::Escape => match c {
+                '[' => self.collect(c, State::Csi),
+                // Strings, whose payload would otherwise show as text
+                ']' | 'P' | 'X' | '^' | '_' if self.sequence.len() == 1 => {
+                    self.collect(c, State::Osc)
+                }
+                // Intermediates, e.g. ESC ( B
+                ' '..='/' => self.collect(c, State::Escape),
+                '0'..='~' => self.finish(c, false, output),
+                _ => self.abort(c, output),
+            },
+            State::Csi => match c {
+                ' '..='?' => self.collect(c, State::Csi),
+                '@'..='~' => self.finish(c, c == 'm', output),
+                _ => self.abort(c, output),
+            },
+            State::Osc => match c {
+                '\x07' => self.finish(c, false, output),
+                '\x1b' => self.collect(c, State::OscEscape),
+                _ if c.is_control() => self.abort(c, output),
+                _ => self.collect(c, State::Osc),
+            },
+            State::OscEscape => match c {
+                '\\' => self.finish(c, false, output),
+                _ => self.abort(c, output),
+            },
+        }
+
+        if self.sequence.len() > MAX_SEQUENCE {
+            let sequence = mem::take(&mut self.sequence);
+            self.state = State::Text;
+            self.push_stray(&sequence, output);
+        }
+    }
+
+    fn push_text(&mut self, c: char, output: &mut String) {
+        let returned = mem::take(&mut self.returned);
+
+        match self.policy.returns {
+            // A held \r is only shown once we know it isn't part of \r\n,
+            // so output never ends at column 0 over the shell box
+            Returns::Keep if returned && c != '\n' => output.push('\r'),
+            // \r already started a new line
+            Returns::Newline if returned && c == '\n' => return,
+            _ => {}
+        }
+
+        match c {
+            '\n' | '\t' => output.push(c),
+            '\r' => {
+                self.returned = true;
+                if self.policy.returns == Returns::Newline {
+                    output.push('\n');
+                }
+            }
+            '\x08' => {
+                if self.policy.backspaces == Backspaces::Interpret {
+                    output.push(c);
+                }
+            }
+            '\x1b' => self.collect(c, State::Escape),
+            _ if c.is_control() => self.push_control(c, output),
+            _ => output.push(c),
+        }
+    }
+
+    fn push_control(&self, c: char, output: &mut String) {
+        match (self.policy.controls, c) {
+            (Controls::Caret, '\0'..='\x1f') => {
+                output.push('^');
+                output.push((c as u8 + 0x40) as char);
+            }
+            (Controls::Caret, '\x7f') => output.push_str("^?"),
+            _ => output.push_str(&format!("<0x{:02x}>", c as u32)),
+        }
+    }
+
+    // Escape sequence that never completed, shown as text
+    fn push_stray(&self, sequence: &str, output: &mut String) {
+        let mut chars = sequence.chars();
+        if let Some(c) = chars.next() {
+            self.push_control(c, output);
+        }
+        output.extend(chars.filter(|c| !c.is_control()));
+    }
+
+    fn collect(&mut self, c: char, state: State) {
+        self.sequence.push(c);
+        self.state = state;
+    }
+
+    fn finish(&mut self, c: char, color: bool, output: &mut String) {
+        self.sequence.push(c);
+        self.state = State::Text;
+
+        let keep = match self.policy.escapes {
+            Escapes::Pass => true,
+            Escapes::Color => color,
+            Escapes::Strip => false,
+        };
+        let sequence = mem::take(&mut self.sequence);
+        if keep {
+            output.push_str(&sequence);
+        }
+    }
+
+    fn abort(&mut self, c: char, output: &mut String) {
+        let sequence = mem::take(&mut self.sequence);
+        self.state = State::Text;
+        self.push_stray(&sequence, output);
+        self.push(c, output);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Controls, Escapes, Invalid, Normalizer, Policy, Returns};
+
+    #[test]
+    fn line_endings() {
+        let mut normalizer = Normalizer::default();
+        assert_eq!(
+            "a\nb\nc\n",
+            normalizer.normalize_bytes("a\r\nb\rc\n".as_bytes())
+        );
+        // \r\n split across prints is one line ending
+        assert_eq!("a\n", normalizer.normalize_bytes("a\r".as_bytes()));
+        assert_eq!("b", normalizer.normalize_bytes("\nb".as_bytes()));
+
+        let mut normalizer = Normalizer::new(Policy {
+            returns: Returns::Keep,
+            ..Policy::default()
+        });
+        assert_eq!("10%", normalizer.normalize_bytes("10%\r".as_bytes()));
+        assert_eq!("\r20%\n", normalizer.normalize_bytes("20%\r\n".as_bytes()));
+    }
+
+    #[test]
+    fn controls() {
+        let mut normalizer = Normalizer::default();
+        assert_eq!(
+            "ab\x08c^G^?",
+            normalizer.normalize_bytes("ab\x08c\x07\x7f".as_bytes())
+        );
+
+        let mut normalizer = Normalizer::new(Policy {
+            controls: Controls::Hex,
+            ..Policy::default()
+        });
+        assert_eq!(
+            "<0x07><0x9b>",
+            normalizer.normalize_bytes("\x07\u{9b}".as_bytes())
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        let mut normalizer = Normalizer::default();
+        let bytes = "héllo".as_bytes();
+
+        assert_eq!("h", normalizer.normalize_bytes(&bytes[..2]));
+        assert_eq!("éllo", normalizer.normalize_bytes(&bytes[2..]));
+        assert_eq!("a<0xff>b", normalizer.normalize_bytes(b"a\xffb"));
+
+        let mut normalizer = Normalizer::new(Policy {
+            invalid: Invalid::Replace,
+            ..Policy::default()
+        });
+        assert_eq!("a\u{fffd}b", normalizer.normalize_bytes(b"a\xffb"));
+    }
+
+    #[test]
+    fn visible() {
+        let normalizer = Normalizer::default();
+        assert_eq!("ls\n^C^[[A^?", normalizer.visible("ls\r\n\x03\x1b[A\x7f"));
+    }
+
+    #[test]
+    fn escapes() {
+        let mut normalizer = Normalizer::default();
+        assert_eq!(
+            "\x1b[1;31mred\x1b[0m",
+            normalizer.normalize_bytes("\x1b[2J\x1b[1;31mred\x1b[0m\x1b]0;title\x07".as_bytes())
+        );
+        // Split across prints
+        assert_eq!("", normalizer.normalize_bytes("\x1b[3".as_bytes()));
+        assert_eq!("\x1b[32m", normalizer.normalize_bytes("2m".as_bytes()));
+        // Stray ESC is shown
+        assert_eq!("^[\n", normalizer.normalize_bytes("\x1b\n".as_bytes()));
+
+        let mut normalizer = Normalizer::new(Policy {
+            escapes: Escapes::Strip,
+            ..Policy::default()
+        });
+        assert_eq!(
+            "red",
+            normalizer.normalize_bytes("\x1b[31mred\x1b[0m".as_bytes())
+        );
+        // DCS, SOS, PM and APC payloads are dropped with the sequence
+        assert_eq!(
+            "abcd",
+            normalizer.normalize_bytes(
+                "a\x1bP1$r0m\x1b\\b\x1bXsos\x07c\x1b^pm\x1b\\\x1b_apc\x1b\\d".as_bytes()
+            )
+        );
+        // ESC ( P is a complete sequence, not a string
+        assert_eq!("e", normalizer.normalize_bytes("\x1b(Pe".as_bytes()));
+    }
+}
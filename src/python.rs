@@ -0,0 +1,382 @@
+use crossterm::style;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::time::Duration;
+
+use crate::completion::Completer;
+use crate::console::{Action, Console, Printer};
+use crate::custom::Handler;
+use crate::mode::{Message, Mode};
+use crate::validator::Validator;
+use crate::Error;
+
+impl From<Error> for PyErr {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Value(_) => PyValueError::new_err(value.to_string()),
+            _ => PyRuntimeError::new_err(value.to_string()),
+        }
+    }
+}
+
+#[pyclass(name = "Action")]
+pub enum PyAction {
+    Writeline(String),
+    Write(String),
+    WriteBytes(Vec<u8>),
+    Quit(),
+}
+
+impl From<Action> for PyAction {
+    fn from(value: Action) -> Self {
+        match value {
+            Action::Writeline(line) => Self::Writeline(line),
+            Action::Write(value) => Self::Write(value),
+            Action::WriteBytes(bytes) => Self::WriteBytes(bytes),
+            Action::Quit() => Self::Quit(),
+        }
+    }
+}
+
+// Python callable taking (line, cursor) and returning a sequence of candidates
+struct PyCompleter(PyObject);
+
+impl Completer for PyCompleter {
+    fn complete(&self, line: &str, cursor: usize) -> Vec<String> {
+        Python::with_gil(|py| {
+            self.0
+                .call1(py, (line, cursor))
+                .and_then(|candidates| candidates.extract::<Vec<String>>(py))
+        })
+        .unwrap_or_else(|err| {
+            log::warn!("completer failed: {}", err);
+            Vec::new()
+        })
+    }
+}
+
+// Python callable taking the input and returning whether it is complete
+struct PyValidator(PyObject);
+
+impl Validator for PyValidator {
+    fn is_complete(&self, input: &str) -> bool {
+        Python::with_gil(|py| {
+            self.0
+                .call1(py, (input,))
+                .and_then(|complete| complete.is_truthy(py))
+        })
+        .unwrap_or_else(|err| {
+            // Never trap the user in an unsubmittable line
+            log::warn!("validator failed: {}", err);
+            true
+        })
+    }
+}
+
+// Python callable taking a key name and returning an Action, a mode name, or None
+struct PyHandler(PyObject);
+
+impl Handler for PyHandler {
+    fn on_key(&self, key: &str) -> Option<Message> {
+        Python::with_gil(|py| -> PyResult<Option<Message>> {
+            let result = self.0.call1(py, (key,))?.into_bound(py);
+
+            if result.is_none() {
+                return Ok(None);
+            }
+            if let Ok(name) = result.extract::<String>() {
+                return Ok(Some(Message::ChangeMode(Mode::from_name(&name))));
+            }
+
+            let message = match &*result.downcast::<PyAction>()?.borrow() {
+                PyAction::Writeline(line) => Message::Writeline(line.to_string()),
+                PyAction::Write(value) => Message::Write(value.to_string()),
+                PyAction::WriteBytes(bytes) => Message::WriteBytes(bytes.clone()),
+                PyAction::Quit() => Message::Quit(),
+            };
+            Ok(Some(message))
+        })
+        .unwrap_or_else(|err| {
+            log::warn!("mode handler failed: {}", err);
+            None
+        })
+    }
+}
+
+// Cloneable handle for printing from other threads, shown on the console's next update
+#[pyclass(name = "Printer")]
+#[derive(Clone)]
+pub struct PyPrinter(Printer);
+
+#[pymethods]
+impl PyPrinter {
+    pub fn print(&self, output: &str) {
+        self.0.print(output);
+    }
+
+    pub fn print_bytes(&self, output: &[u8]) {
+        self.0.print_bytes(output);
+    }
+}
+
+// Mode handlers, completers and validators run while the console is busy polling, so
+// the console is locked rather than borrowed, and print falls back to the printer
+#[pyclass(name = "Console", frozen)]
+pub struct PyConsole {
+    console: Mutex<Console>,
+    printer: Printer,
+}
+
+impl PyConsole {
+    // None while the console is busy, e.g. running a mode handler
+    fn try_console(&self) -> Option<MutexGuard<'_, Console>> {
+        match self.console.try_lock() {
+            Ok(console) => Some(console),
+            // A panic mid-update leaves the console usable
+            Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    fn console(&self) -> PyResult<MutexGuard<'_, Console>> {
+        self.try_console().ok_or_else(|| {
+            PyRuntimeError::new_err(
+                "console is busy running a mode handler, completer or validator",
+            )
+        })
+    }
+}
+
+#[pymethods]
+impl PyConsole {
+    #[new]
+    #[pyo3(signature = (name, titles, terminator = None))]
+    pub fn new(name: String, titles: Vec<String>, terminator: Option<&str>) -> PyResult<Self> {
+        let mut console = Console::new(&name, &titles)?;
+        if let Some(terminator) = terminator {
+            console.set_terminator(terminator.parse()?);
+        }
+        Ok(Self {
+            printer: console.printer(),
+            console: Mutex::new(console),
+        })
+    }
+
+    // e.g. "\r\n", to append to Writeline lines before sending them
+    #[getter]
+    pub fn terminator(&self) -> PyResult<&'static str> {
+        Ok(self.console()?.terminator().as_str())
+    }
+
+    #[pyo3(signature = (returns = None, backspaces = None, escapes = None, controls = None, invalid = None))]
+    pub fn set_output_policy(
+        &self,
+        returns: Option<&str>,
+        backspaces: Option<&str>,
+        escapes: Option<&str>,
+        controls: Option<&str>,
+        invalid: Option<&str>,
+    ) -> PyResult<()> {
+        let mut console = self.console()?;
+        let mut policy = console.output_policy();
+        if let Some(returns) = returns {
+            policy.returns = returns.parse()?;
+        }
+        if let Some(backspaces) = backspaces {
+            policy.backspaces = backspaces.parse()?;
+        }
+        if let Some(escapes) = escapes {
+            policy.escapes = escapes.parse()?;
+        }
+        if let Some(controls) = controls {
+            policy.controls = controls.parse()?;
+        }
+        if let Some(invalid) = invalid {
+            policy.invalid = invalid.parse()?;
+        }
+        console.set_output_policy(policy);
+        Ok(())
+    }
+
+    pub fn start(&self) -> PyResult<()> {
+        Ok(self.console()?.start()?)
+    }
+
+    pub fn stop(&self) -> PyResult<()> {
+        Ok(self.console()?.stop()?)
+    }
+
+    pub fn update(&self, py: Python<'_>, timeout_ns: u64) -> PyResult<Option<PyAction>> {
+        let waiter = {
+            let mut console = self.console()?;
+            if let Some(action) = console.poll()? {
+                return Ok(Some(action.into()));
+            }
+            console.waiter()
+        };
+
+        // Not locked while waiting, so other threads can use the console
+        py.allow_threads(|| waiter.wait(Duration::from_nanos(timeout_ns)));
+
+        Ok(self.console()?.poll()?.map(PyAction::from))
+    }
+
+    // Resolves with the next action, for use from asyncio
+    pub async fn next_action(slf: Py<Self>) -> PyResult<PyAction> {
+        loop {
+            // Only locked between awaits, so coroutines can print meanwhile
+            let (action, waiter) = {
+                let mut console = slf.get().console()?;
+                (console.poll()?, console.waiter())
+            };
+
+            match action {
+                Some(action) => return Ok(action.into()),
+                None => waiter.ready().await,
+            }
+        }
+    }
+
+    #[pyo3(signature = (completer))]
+    pub fn set_completer(&self, completer: Option<PyObject>) -> PyResult<()> {
+        self.console()?.set_completer(
+            completer.map(|completer| Box::new(PyCompleter(completer)) as Box<dyn Completer>),
+        );
+        Ok(())
+    }
+
+    #[pyo3(signature = (validator))]
+    pub fn set_validator(&self, validator: Option<PyObject>) -> PyResult<()> {
+        self.console()?.set_validator(
+            validator.map(|validator| Box::new(PyValidator(validator)) as Box<dyn Validator>),
+        );
+        Ok(())
+    }
+
+    #[pyo3(signature = (name, key, handler, color = "blue", keybinds = Vec::new()))]
+    pub fn add_mode(
+        &self,
+        name: String,
+        key: char,
+        handler: PyObject,
+        color: &str,
+        keybinds: Vec<String>,
+    ) -> PyResult<()> {
+        let color = style::Color::try_from(color)
+            .map_err(|_| Error::Value(format!("unknown color {:?}", color)))?;
+
+        self.console()?
+            .add_mode(name, key, color, keybinds, Box::new(PyHandler(handler)))?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn dump(&self) -> PyResult<bool> {
+        Ok(self.console()?.dump())
+    }
+
+    #[setter]
+    pub fn set_dump(&self, dump: bool) -> PyResult<()> {
+        self.console()?.set_dump(dump);
+        Ok(())
+    }
+
+    #[getter]
+    pub fn echo(&self) -> PyResult<bool> {
+        Ok(self.console()?.echo())
+    }
+
+    #[setter]
+    pub fn set_echo(&self, echo: bool) -> PyResult<()> {
+        self.console()?.set_echo(echo);
+        Ok(())
+    }
+
+    pub fn set_key_encoding(&self, encoding: &str) -> PyResult<()> {
+        self.console()?.set_key_encoding(encoding.parse()?);
+        Ok(())
+    }
+
+    pub fn set_edit_mode(&self, mode: &str) -> PyResult<()> {
+        self.console()?.set_edit_mode(mode.parse()?);
+        Ok(())
+    }
+
+    pub fn set_word_style(&self, style: &str) -> PyResult<()> {
+        self.console()?.set_word_style(style.parse()?);
+        Ok(())
+    }
+
+    pub fn printer(&self) -> PyPrinter {
+        PyPrinter(self.printer.clone())
+    }
+
+    // Queued for the next update instead while the console is busy, e.g. from a mode handler
+    pub fn print(&self, output: &str) -> PyResult<()> {
+        self.print_bytes(output.as_bytes())
+    }
+
+    pub fn print_bytes(&self, output: &[u8]) -> PyResult<()> {
+        match self.try_console() {
+            Some(mut console) => Ok(console.print_bytes(output)?),
+            None => {
+                self.printer.print_bytes(output);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[pymodule]
+fn exoshell(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    env_logger::init();
+    m.add_class::<PyAction>()?;
+    m.add_class::<PyConsole>()?;
+    m.add_class::<PyPrinter>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PyAction, PyConsole, PyHandler, PyValidator};
+    use crate::custom::Handler;
+    use crate::mode::Message;
+    use crate::validator::Validator;
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+    use std::env;
+
+    #[test]
+    fn callbacks_print_while_console_is_busy() {
+        env::set_var("EXOSHELL_DATA_DIR", env::temp_dir().join("exoshell-test"));
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let console = PyConsole::new("test".into(), Vec::new(), None).unwrap();
+            let console = Py::new(py, console).unwrap();
+
+            let globals = PyDict::new(py);
+            globals.set_item("console", &console).unwrap();
+            globals
+                .set_item("Action", py.get_type::<PyAction>())
+                .unwrap();
+            py.run(
+                c"def handler(key):\n    console.print('poke ' + key)\n    return Action.Write(key)\n\
+                  def validator(input):\n    console.print(input)\n    return False\n",
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+            let callback = |name: &str| globals.get_item(name).unwrap().unwrap().unbind();
+
+            // As while polling, where callbacks run
+            let _busy = console.get().console().unwrap();
+
+            let handler = PyHandler(callback("handler"));
+            assert!(matches!(handler.on_key("a"), Some(Message::Write(key)) if key == "a"));
+            assert!(!PyValidator(callback("validator")).is_complete("x"));
+            assert!(console.get().console().is_err());
+        });
+    }
+}
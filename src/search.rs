@@ -0,0 +1,90 @@
+use std::ops::Range;
+
+use crate::history::{self, History};
+
+// Reverse incremental search over history, ranked by frecency
+#[derive(Debug, Default)]
+pub struct Search {
+    query: String,
+    matches: Vec<String>,
+    index: usize,
+    // Rendered prompt, e.g. `query': match
+    contents: String,
+    highlight: Option<Range<usize>>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        let mut self_ = Self::default();
+        self_.render();
+        self_
+    }
+
+    pub fn push(&mut self, c: char, history: &History) {
+        self.query.push(c);
+        self.refresh(history);
+    }
+
+    pub fn pop(&mut self, history: &History) {
+        self.query.pop();
+        self.refresh(history);
+    }
+
+    pub fn older(&mut self) {
+        self.index = (self.index + 1).min(self.matches.len().saturating_sub(1));
+        self.render();
+    }
+
+    pub fn newer(&mut self) {
+        self.index = self.index.saturating_sub(1);
+        self.render();
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.matches.get(self.index).map(|cmd| cmd.as_str())
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub fn cursor(&self) -> usize {
+        // Cursor sits at the end of the query, after the opening backtick
+        self.query.chars().count() + 1
+    }
+
+    pub fn highlight(&self) -> Option<Range<usize>> {
+        self.highlight.clone()
+    }
+
+    fn refresh(&mut self, history: &History) {
+        self.matches = if self.query.is_empty() {
+            Vec::new()
+        } else {
+            let now = history::now().unwrap_or_default();
+            history
+                .search(&self.query, now)
+                .into_iter()
+                .map(|entry| entry.cmd.to_string())
+                .collect()
+        };
+        self.index = 0;
+        self.render();
+    }
+
+    fn render(&mut self) {
+        let prefix = format!("`{}': ", self.query);
+        let offset = prefix.chars().count();
+
+        self.highlight = None;
+        self.contents = prefix;
+
+        if let Some(cmd) = self.matches.get(self.index) {
+            if let Some(byte_index) = cmd.find(&self.query) {
+                let start = offset + cmd[..byte_index].chars().count();
+                self.highlight = Some(start..start + self.query.chars().count());
+            }
+            self.contents.push_str(cmd);
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use crate::banner::{Banner, Component};
+use crate::Result;
 use crossterm::{
     cursor,
     style::{self, Stylize},
     terminal, QueueableCommand,
 };
 use std::io;
-use unicode_width::UnicodeWidthChar;
+use std::ops::Range;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Maximum number of menu rows shown at once
+const MENU_ROWS: usize = 6;
 
 struct Border {
     horizontal: char,
@@ -35,6 +40,46 @@ pub trait State {
     fn contents(&self) -> &str;
     fn name(&self) -> &str;
     fn keybinds(&self) -> Vec<&str>;
+
+    // Character range of contents to render highlighted
+    fn highlight(&self) -> Option<Range<usize>> {
+        None
+    }
+
+    // Ghost text rendered after contents, e.g. a history suggestion
+    fn suggestion(&self) -> &str {
+        ""
+    }
+
+    // Completion candidates rendered as rows above the footer
+    fn menu(&self) -> &[String] {
+        &[]
+    }
+
+    fn menu_selected(&self) -> Option<usize> {
+        None
+    }
+}
+
+// Truncate or pad value with spaces to exactly width columns
+fn fit(value: &str, width: usize) -> String {
+    let mut output = String::new();
+    let mut cols = 0;
+
+    for c in value.chars() {
+        let char_cols = UnicodeWidthChar::width(c).unwrap_or(0);
+        if cols + char_cols > width {
+            break;
+        }
+        cols += char_cols;
+        output.push(c);
+    }
+
+    for _ in cols..width {
+        output.push(' ');
+    }
+
+    output
 }
 
 pub struct Shell {
@@ -46,7 +91,7 @@ pub struct Shell {
 }
 
 impl Shell {
-    pub fn new(cols: impl Into<usize>) -> io::Result<Self> {
+    pub fn new(cols: impl Into<usize>) -> Result<Self> {
         let border = Border::create(boxy::Weight::Normal, boxy::Style::Curved);
 
         Ok(Self {
@@ -61,16 +106,17 @@ impl Shell {
         self.titles.push(title.to_string());
     }
 
-    pub fn write(
-        &mut self,
-        stream: &mut impl QueueableCommand,
-        state: &impl State,
-    ) -> io::Result<()> {
+    pub fn write(&mut self, stream: &mut impl QueueableCommand, state: &impl State) -> Result<()> {
         // All relative to inner content
         let width = self.cols - 2;
         let contents = state.contents().to_string();
         let color = state.color();
         let cursor = state.cursor();
+        let highlight = state.highlight().unwrap_or_default();
+        let suggestion = state.suggestion();
+
+        let contents_len = contents.chars().count();
+        let ghost = contents_len..contents_len + suggestion.chars().count();
 
         let left = '(';
         let right = ')';
@@ -100,33 +146,62 @@ impl Shell {
         let mut cursor_row = 0;
         let mut cursor_col = 0;
 
-        for (index, c) in contents.chars().chain(vec![' ']).enumerate() {
-            let char_cols = UnicodeWidthChar::width(c).unwrap_or(0);
+        for (index, c) in contents
+            .chars()
+            .chain(suggestion.chars())
+            .chain(vec![' '])
+            .enumerate()
+        {
+            if c == '\n' {
+                if index == cursor {
+                    cursor_row = current_row;
+                    cursor_col = current_col;
+                }
 
-            if index == cursor {
-                cursor_row = current_row;
-                cursor_col = current_col;
+                // embedded newlines end the row early
+                stream.queue(style::Print(fit("", width.saturating_sub(current_col))))?;
+                stream.queue(style::PrintStyledContent(
+                    format!("{0}\r\n{0}", self.border.vertical).with(color),
+                ))?;
+
+                current_col = 0;
+                current_row += 1;
+                continue;
             }
 
-            if current_col + char_cols <= width {
-                // if it fits, print it
-                current_col += char_cols;
-                stream.queue(style::Print(c))?;
+            let char_cols = UnicodeWidthChar::width(c).unwrap_or(0);
+
+            let styled = if highlight.contains(&index) {
+                c.reverse()
+            } else if ghost.contains(&index) {
+                c.dark_grey()
             } else {
+                c.stylize()
+            };
+
+            if current_col + char_cols > width {
                 // pad with spaces if we can't fit a full character
                 for _ in current_col..width {
                     stream.queue(style::Print(" "))?;
                 }
 
-                current_col = char_cols;
+                current_col = 0;
                 current_row += 1;
 
                 // print the border character instead
                 stream.queue(style::PrintStyledContent(
                     format!("{0}\r\n{0}", self.border.vertical).with(color),
                 ))?;
-                stream.queue(style::Print(format!("{}", c)))?;
             }
+
+            // record after wrapping, so a wrapped cursor lands on the new row
+            if index == cursor {
+                cursor_row = current_row;
+                cursor_col = current_col;
+            }
+
+            current_col += char_cols;
+            stream.queue(style::PrintStyledContent(styled))?;
         }
 
         self.cursor = (cursor_row, cursor_col); // (row, col)
@@ -139,6 +214,8 @@ impl Shell {
             format!("{}\r\n", self.border.vertical).with(color),
         ))?;
 
+        let menu_rows = self.write_menu(stream, state, width, color)?;
+
         let mut footer = Banner::new(self.border.horizontal).push_left(Component::new(
             left,
             state.name(),
@@ -159,13 +236,71 @@ impl Shell {
                 )
                 .with(color),
             ))?
-            .queue(cursor::MoveUp((current_row - cursor_row + 1) as u16))?
+            .queue(cursor::MoveUp(
+                (current_row - cursor_row + menu_rows + 1) as u16,
+            ))?
             .queue(cursor::MoveRight((cursor_col + 1) as u16))?;
 
         Ok(())
     }
 
-    pub fn clear(&self, stream: &mut impl QueueableCommand) -> io::Result<()> {
+    // Lay out menu items in columns, paging so the selected item is visible
+    fn write_menu(
+        &self,
+        stream: &mut impl QueueableCommand,
+        state: &impl State,
+        width: usize,
+        color: style::Color,
+    ) -> Result<usize> {
+        let items = state.menu();
+        let selected = state.menu_selected();
+
+        if items.is_empty() || width == 0 {
+            return Ok(0);
+        }
+
+        // Two spaces between columns
+        let item_cols = items
+            .iter()
+            .map(|item| UnicodeWidthStr::width(item.as_str()))
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let columns = (width / item_cols).max(1);
+        let page_size = columns * MENU_ROWS;
+        let page = selected.unwrap_or(0) / page_size;
+
+        let page_items: Vec<_> = items
+            .iter()
+            .enumerate()
+            .skip(page * page_size)
+            .take(page_size)
+            .collect();
+
+        for row in page_items.chunks(columns) {
+            stream.queue(style::PrintStyledContent(self.border.vertical.with(color)))?;
+
+            let mut current_col = 0;
+            for (index, item) in row {
+                let cell = fit(item, item_cols.min(width - current_col));
+                current_col += UnicodeWidthStr::width(cell.as_str());
+
+                match selected == Some(*index) {
+                    true => stream.queue(style::PrintStyledContent(cell.reverse()))?,
+                    false => stream.queue(style::Print(cell))?,
+                };
+            }
+
+            stream.queue(style::Print(fit("", width - current_col)))?;
+            stream.queue(style::PrintStyledContent(
+                format!("{}\r\n", self.border.vertical).with(color),
+            ))?;
+        }
+
+        Ok(page_items.len().div_ceil(columns))
+    }
+
+    pub fn clear(&self, stream: &mut impl QueueableCommand) -> Result<()> {
         let (cursor_row, _) = self.cursor;
 
         stream.queue(cursor::MoveUp((cursor_row + 1) as u16))?;
@@ -175,7 +310,7 @@ impl Shell {
         Ok(())
     }
 
-    pub fn resize(&mut self, cols: impl Into<usize>) -> io::Result<()> {
+    pub fn resize(&mut self, cols: impl Into<usize>) -> Result<()> {
         let cols = cols.into();
 
         // If zoom in, then we need to clear a few extra lines due to word wrap
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+// Line ending sent after each submitted line
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Terminator {
+    #[default]
+    Lf,
+    Cr,
+    CrLf,
+    None,
+}
+
+impl Terminator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Cr => "\r",
+            Self::CrLf => "\r\n",
+            Self::None => "",
+        }
+    }
+}
+
+impl FromStr for Terminator {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "lf" => Ok(Self::Lf),
+            "cr" => Ok(Self::Cr),
+            "crlf" => Ok(Self::CrLf),
+            "none" => Ok(Self::None),
+            _ => Err(Error::Value(format!(
+                "unknown terminator {:?}, expected \"lf\", \"cr\", \"crlf\" or \"none\"",
+                value
+            ))),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub contents: String,
+    pub cursor: usize,
+}
+
+// Consecutive edits of the same group are undone as a single step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Group {
+    Insert,
+    History,
+}
+
+#[derive(Debug, Default)]
+pub struct Undo {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    group: Option<Group>,
+}
+
+impl Undo {
+    // Record an edit from before to after, if anything changed
+    pub fn record(&mut self, before: Snapshot, after: &Snapshot, group: Option<Group>) {
+        if before.contents == after.contents {
+            // Moving the cursor ends the current group
+            if before.cursor != after.cursor {
+                self.group = None;
+            }
+            return;
+        }
+
+        if group.is_none() || group != self.group {
+            self.undo.push(before);
+        }
+        self.group = group;
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.group = None;
+        Some(snapshot)
+    }
+
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.group = None;
+        Some(snapshot)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Group, Snapshot, Undo};
+
+    fn snapshot(contents: &str) -> Snapshot {
+        Snapshot {
+            contents: contents.to_string(),
+            cursor: contents.chars().count(),
+        }
+    }
+
+    #[test]
+    fn groups_consecutive_inserts() {
+        let mut undo = Undo::default();
+        undo.record(snapshot(""), &snapshot("a"), Some(Group::Insert));
+        undo.record(snapshot("a"), &snapshot("ab"), Some(Group::Insert));
+        undo.record(snapshot("ab"), &snapshot("a"), None);
+
+        assert_eq!(Some(snapshot("ab")), undo.undo(snapshot("a")));
+        assert_eq!(Some(snapshot("")), undo.undo(snapshot("ab")));
+        assert_eq!(None, undo.undo(snapshot("")));
+    }
+
+    #[test]
+    fn cursor_motion_ends_group() {
+        let mut undo = Undo::default();
+        undo.record(snapshot(""), &snapshot("a"), Some(Group::Insert));
+        undo.record(
+            snapshot("a"),
+            &Snapshot {
+                contents: "a".to_string(),
+                cursor: 0,
+            },
+            None,
+        );
+        undo.record(snapshot("a"), &snapshot("ba"), Some(Group::Insert));
+
+        assert_eq!(Some(snapshot("a")), undo.undo(snapshot("ba")));
+    }
+
+    #[test]
+    fn redo_after_undo() {
+        let mut undo = Undo::default();
+        undo.record(snapshot(""), &snapshot("a"), None);
+
+        assert_eq!(Some(snapshot("")), undo.undo(snapshot("a")));
+        assert_eq!(Some(snapshot("a")), undo.redo(snapshot("")));
+        assert_eq!(None, undo.redo(snapshot("a")));
+    }
+
+    #[test]
+    fn edit_clears_redo() {
+        let mut undo = Undo::default();
+        undo.record(snapshot(""), &snapshot("a"), None);
+        undo.undo(snapshot("a"));
+        undo.record(snapshot(""), &snapshot("b"), None);
+
+        assert_eq!(None, undo.redo(snapshot("b")));
+    }
+}
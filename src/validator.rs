@@ -0,0 +1,4 @@
+// Decides whether Enter submits the line or starts a new row
+pub trait Validator: Send + Sync {
+    fn is_complete(&self, input: &str) -> bool;
+}
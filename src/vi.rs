@@ -0,0 +1,657 @@
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+// Key bindings used by the line editor
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EditMode {
+    #[default]
+    Emacs,
+    Vi,
+}
+
+impl FromStr for EditMode {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "emacs" => Ok(Self::Emacs),
+            "vi" => Ok(Self::Vi),
+            _ => Err(Error::Value(format!(
+                "unknown edit mode {:?}, expected \"emacs\" or \"vi\"",
+                value
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    WordNext,
+    WordPrev,
+    WordEnd,
+    Start,
+    End,
+    Find {
+        target: char,
+        till: bool,
+        backward: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Insert {
+    Before,
+    After,
+    Start,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Move(Motion),
+    Operate(Operator, Motion),
+    // Doubled operator, e.g. dd
+    OperateLine(Operator),
+    DeleteChar,
+    Put { before: bool },
+    Insert(Insert),
+    Undo,
+    Repeat,
+    Older,
+    Newer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Command {
+    count: Option<usize>,
+    action: Action,
+}
+
+impl Command {
+    fn count(&self) -> usize {
+        self.count.unwrap_or(1)
+    }
+
+    // Whether `.` should repeat this command
+    fn is_change(&self) -> bool {
+        match self.action {
+            Action::Operate(operator, _) | Action::OperateLine(operator) => {
+                operator != Operator::Yank
+            }
+            Action::DeleteChar | Action::Put { .. } | Action::Insert(_) => true,
+            _ => false,
+        }
+    }
+}
+
+enum Parse<T> {
+    Incomplete,
+    Invalid,
+    Done(T),
+}
+
+// Counts past this are treated as this, so huge counts can't overflow or hang
+const MAX_COUNT: usize = 9999;
+
+// Leading count, which can't start with 0 since that's a motion
+fn parse_count(keys: &[char]) -> (Option<usize>, &[char]) {
+    let digits = match keys.first() {
+        Some('1'..='9') => keys.iter().take_while(|c| c.is_ascii_digit()).count(),
+        _ => 0,
+    };
+
+    let count = keys[..digits].iter().try_fold(0usize, |count, c| {
+        let count = count * 10 + c.to_digit(10)? as usize;
+        Some(count.min(MAX_COUNT))
+    });
+    (count.filter(|_| digits > 0), &keys[digits..])
+}
+
+fn parse_motion(keys: &[char]) -> Parse<Motion> {
+    let Some(first) = keys.first() else {
+        return Parse::Incomplete;
+    };
+
+    let motion = match first {
+        'h' => Motion::Left,
+        'l' | ' ' => Motion::Right,
+        'w' => Motion::WordNext,
+        'b' => Motion::WordPrev,
+        'e' => Motion::WordEnd,
+        '0' | '^' => Motion::Start,
+        '$' => Motion::End,
+        'f' | 't' | 'F' | 'T' => match keys.get(1) {
+            Some(target) => Motion::Find {
+                target: *target,
+                till: matches!(first, 't' | 'T'),
+                backward: matches!(first, 'F' | 'T'),
+            },
+            None => return Parse::Incomplete,
+        },
+        _ => return Parse::Invalid,
+    };
+
+    Parse::Done(motion)
+}
+
+fn parse(keys: &[char]) -> Parse<Command> {
+    let (count, keys) = parse_count(keys);
+    let Some(first) = keys.first() else {
+        return Parse::Incomplete;
+    };
+
+    let action = match first {
+        'x' => Action::DeleteChar,
+        'p' => Action::Put { before: false },
+        'P' => Action::Put { before: true },
+        'u' => Action::Undo,
+        '.' => Action::Repeat,
+        'i' => Action::Insert(Insert::Before),
+        'a' => Action::Insert(Insert::After),
+        'I' => Action::Insert(Insert::Start),
+        'A' => Action::Insert(Insert::End),
+        'k' => Action::Older,
+        'j' => Action::Newer,
+        'D' => Action::Operate(Operator::Delete, Motion::End),
+        'C' => Action::Operate(Operator::Change, Motion::End),
+
+        'd' | 'c' | 'y' => {
+            let operator = match first {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+
+            // Counts multiply, e.g. 2d3w deletes 6 words
+            let (motion_count, keys) = parse_count(&keys[1..]);
+            let count = match (count, motion_count) {
+                (None, None) => None,
+                (count, motion_count) => Some(
+                    count
+                        .unwrap_or(1)
+                        .saturating_mul(motion_count.unwrap_or(1))
+                        .min(MAX_COUNT),
+                ),
+            };
+
+            let action = match keys.first() {
+                Some(c) if c == first => Action::OperateLine(operator),
+                _ => match parse_motion(keys) {
+                    Parse::Done(motion) => Action::Operate(operator, motion),
+                    Parse::Incomplete => return Parse::Incomplete,
+                    Parse::Invalid => return Parse::Invalid,
+                },
+            };
+
+            return Parse::Done(Command { count, action });
+        }
+
+        _ => match parse_motion(keys) {
+            Parse::Done(motion) => Action::Move(motion),
+            Parse::Incomplete => return Parse::Incomplete,
+            Parse::Invalid => return Parse::Invalid,
+        },
+    };
+
+    Parse::Done(Command { count, action })
+}
+
+// Whitespace, word characters and punctuation each form their own words
+fn class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn word_next(chars: &[char], mut index: usize) -> usize {
+    if let Some(&c) = chars.get(index) {
+        let start = class(c);
+        while index < chars.len() && start != 0 && class(chars[index]) == start {
+            index += 1;
+        }
+    }
+    while index < chars.len() && class(chars[index]) == 0 {
+        index += 1;
+    }
+    index
+}
+
+fn word_prev(chars: &[char], mut index: usize) -> usize {
+    while index > 0 && class(chars[index - 1]) == 0 {
+        index -= 1;
+    }
+    if index > 0 {
+        let start = class(chars[index - 1]);
+        while index > 0 && class(chars[index - 1]) == start {
+            index -= 1;
+        }
+    }
+    index
+}
+
+fn word_end(chars: &[char], index: usize) -> usize {
+    let mut index = index + 1;
+    while index < chars.len() && class(chars[index]) == 0 {
+        index += 1;
+    }
+    if index >= chars.len() {
+        return chars.len().saturating_sub(1);
+    }
+    let start = class(chars[index]);
+    while index + 1 < chars.len() && class(chars[index + 1]) == start {
+        index += 1;
+    }
+    index
+}
+
+impl Motion {
+    // Inclusive motions also cover the character they land on when used with an operator
+    fn inclusive(&self) -> bool {
+        match self {
+            Self::WordEnd | Self::End => true,
+            Self::Find { backward, .. } => !backward,
+            _ => false,
+        }
+    }
+
+    fn target(&self, chars: &[char], cursor: usize, count: usize) -> Option<usize> {
+        // No motion can go further than the line is long
+        let count = count.min(chars.len().max(1));
+        let target = match *self {
+            Self::Left => cursor.saturating_sub(count),
+            Self::Right => cursor.saturating_add(count).min(chars.len()),
+            Self::WordNext => (0..count).fold(cursor, |index, _| word_next(chars, index)),
+            Self::WordPrev => (0..count).fold(cursor, |index, _| word_prev(chars, index)),
+            Self::WordEnd => (0..count).fold(cursor, |index, _| word_end(chars, index)),
+            Self::Start => 0,
+            Self::End => chars.len().saturating_sub(1),
+            Self::Find {
+                target,
+                till,
+                backward: false,
+            } => {
+                let found = chars
+                    .iter()
+                    .enumerate()
+                    .skip(cursor + 1)
+                    .filter(|(_, c)| **c == target)
+                    .nth(count - 1)?
+                    .0;
+                if till {
+                    found - 1
+                } else {
+                    found
+                }
+            }
+            Self::Find {
+                target,
+                till,
+                backward: true,
+            } => {
+                let found = chars[..cursor.min(chars.len())]
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, c)| **c == target)
+                    .nth(count - 1)?
+                    .0;
+                if till {
+                    found + 1
+                } else {
+                    found
+                }
+            }
+        };
+
+        Some(target)
+    }
+}
+
+// What the line editor needs to do on behalf of a normal mode command
+#[derive(Debug, PartialEq)]
+pub enum Effect {
+    None,
+    Undo(usize),
+    Older(usize),
+    Newer(usize),
+}
+
+#[derive(Clone, Debug)]
+struct Change {
+    command: Command,
+    // Text typed in insert mode, if the command entered it
+    text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Vi {
+    insert: bool,
+    pending: Vec<char>,
+    register: String,
+    last: Option<Change>,
+    // Command that entered insert mode, and where typing started
+    inserting: Option<(Command, usize)>,
+}
+
+impl Vi {
+    pub fn new() -> Self {
+        Self {
+            insert: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_insert(&self) -> bool {
+        self.insert
+    }
+
+    // Leave insert mode, moving back onto the last typed character
+    pub fn escape(&mut self, contents: &str, cursor: &mut usize) {
+        if !self.insert {
+            self.pending.clear();
+            return;
+        }
+
+        if let Some((command, start)) = self.inserting.take() {
+            let text = contents
+                .chars()
+                .skip(start)
+                .take(cursor.saturating_sub(start))
+                .collect();
+            self.last = Some(Change { command, text });
+        }
+
+        self.insert = false;
+        *cursor = cursor.saturating_sub(1);
+    }
+
+    // Start over in insert mode, e.g. after submitting a line
+    pub fn reset(&mut self) {
+        self.insert = true;
+        self.pending.clear();
+        self.inserting = None;
+    }
+
+    pub fn on_char(&mut self, c: char, contents: &mut String, cursor: &mut usize) -> Effect {
+        self.pending.push(c);
+
+        let command = match parse(&self.pending) {
+            Parse::Incomplete => return Effect::None,
+            Parse::Invalid => {
+                self.pending.clear();
+                return Effect::None;
+            }
+            Parse::Done(command) => command,
+        };
+        self.pending.clear();
+
+        let effect = match command.action {
+            Action::Repeat => match self.last.clone() {
+                Some(mut change) => {
+                    if command.count.is_some() {
+                        change.command.count = command.count;
+                    }
+                    self.replay(change, contents, cursor)
+                }
+                None => Effect::None,
+            },
+            _ => {
+                let effect = self.run(command, contents, cursor);
+                if command.is_change() {
+                    match self.insert {
+                        true => self.inserting = Some((command, *cursor)),
+                        false => {
+                            self.last = Some(Change {
+                                command,
+                                text: String::new(),
+                            })
+                        }
+                    }
+                }
+                effect
+            }
+        };
+
+        // Normal mode sits on a character rather than between them
+        if !self.insert {
+            *cursor = (*cursor).min(contents.chars().count().saturating_sub(1));
+        }
+
+        effect
+    }
+
+    fn replay(&mut self, change: Change, contents: &mut String, cursor: &mut usize) -> Effect {
+        let effect = self.run(change.command, contents, cursor);
+
+        if self.insert {
+            splice(contents, *cursor..*cursor, &change.text);
+            *cursor += change.text.chars().count();
+            self.insert = false;
+            *cursor = cursor.saturating_sub(1);
+        }
+
+        effect
+    }
+
+    fn run(&mut self, command: Command, contents: &mut String, cursor: &mut usize) -> Effect {
+        let chars: Vec<char> = contents.chars().collect();
+        let count = command.count();
+
+        match command.action {
+            Action::Move(motion) => {
+                if let Some(target) = motion.target(&chars, *cursor, count) {
+                    *cursor = target;
+                }
+            }
+
+            Action::Operate(operator, motion) => {
+                // cw on a word behaves like ce, leaving trailing whitespace alone
+                let motion = match (operator, motion, chars.get(*cursor)) {
+                    (Operator::Change, Motion::WordNext, Some(c)) if !c.is_whitespace() => {
+                        Motion::WordEnd
+                    }
+                    _ => motion,
+                };
+
+                let Some(target) = motion.target(&chars, *cursor, count) else {
+                    return Effect::None;
+                };
+
+                let range = if target >= *cursor {
+                    let end = target + motion.inclusive() as usize;
+                    *cursor..end.min(chars.len())
+                } else {
+                    target..*cursor
+                };
+
+                self.operate(operator, range, contents, cursor);
+            }
+
+            Action::OperateLine(operator) => {
+                self.operate(operator, 0..chars.len(), contents, cursor);
+            }
+
+            Action::DeleteChar => {
+                let end = cursor.saturating_add(count).min(chars.len());
+                if *cursor < end {
+                    self.operate(Operator::Delete, *cursor..end, contents, cursor);
+                }
+            }
+
+            Action::Put { before } => {
+                let at = match before {
+                    true => *cursor,
+                    false => (*cursor + 1).min(chars.len()),
+                };
+                let text = self.register.repeat(count);
+                if !text.is_empty() {
+                    splice(contents, at..at, &text);
+                    *cursor = at + text.chars().count() - 1;
+                }
+            }
+
+            Action::Insert(insert) => {
+                *cursor = match insert {
+                    Insert::Before => *cursor,
+                    Insert::After => (*cursor + 1).min(chars.len()),
+                    Insert::Start => 0,
+                    Insert::End => chars.len(),
+                };
+                self.insert = true;
+            }
+
+            Action::Undo => return Effect::Undo(count),
+            Action::Older => return Effect::Older(count),
+            Action::Newer => return Effect::Newer(count),
+            Action::Repeat => {}
+        }
+
+        Effect::None
+    }
+
+    fn operate(
+        &mut self,
+        operator: Operator,
+        range: std::ops::Range<usize>,
+        contents: &mut String,
+        cursor: &mut usize,
+    ) {
+        self.register = contents
+            .chars()
+            .skip(range.start)
+            .take(range.len())
+            .collect();
+
+        if operator != Operator::Yank {
+            splice(contents, range.clone(), "");
+        }
+        if operator == Operator::Change {
+            self.insert = true;
+        }
+
+        *cursor = range.start;
+    }
+}
+
+fn splice(contents: &mut String, range: std::ops::Range<usize>, value: &str) {
+    let mut chars = contents.chars();
+    let left = chars.by_ref().take(range.start).collect::<String>();
+    let right = chars.skip(range.len()).collect::<String>();
+    *contents = format!("{}{}{}", left, value, right);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Effect, Vi};
+
+    // Type keys in normal mode, starting with the cursor at the end of contents
+    fn normal(contents: &str, keys: &str) -> (String, usize) {
+        let mut vi = Vi::new();
+        let mut contents = contents.to_string();
+        let mut cursor = contents.chars().count();
+        vi.escape(&contents, &mut cursor);
+
+        for c in keys.chars() {
+            if c == '\u{1b}' {
+                vi.escape(&contents, &mut cursor);
+            } else if vi.is_insert() {
+                contents.insert(
+                    contents
+                        .char_indices()
+                        .nth(cursor)
+                        .map_or(contents.len(), |(i, _)| i),
+                    c,
+                );
+                cursor += 1;
+            } else {
+                assert_eq!(Effect::None, vi.on_char(c, &mut contents, &mut cursor));
+            }
+        }
+
+        (contents, cursor)
+    }
+
+    #[test]
+    fn motions() {
+        assert_eq!(("reg read 0x10".into(), 0), normal("reg read 0x10", "0"));
+        assert_eq!(("reg read 0x10".into(), 4), normal("reg read 0x10", "0w"));
+        assert_eq!(("reg read 0x10".into(), 7), normal("reg read 0x10", "0we"));
+        assert_eq!(("reg read 0x10".into(), 9), normal("reg read 0x10", "b"));
+        assert_eq!(("reg read 0x10".into(), 6), normal("reg read 0x10", "0fa"));
+        assert_eq!(("reg read 0x10".into(), 5), normal("reg read 0x10", "0ta"));
+        assert_eq!(("reg read 0x10".into(), 12), normal("reg read 0x10", "0$"));
+        assert_eq!(
+            ("reg read 0x10".into(), 2),
+            normal("reg read 0x10", "0lllh")
+        );
+    }
+
+    #[test]
+    fn operators_with_counts() {
+        assert_eq!(("0x10".into(), 0), normal("reg read 0x10", "0d2w"));
+        assert_eq!(("0x10".into(), 0), normal("reg read 0x10", "02dw"));
+        assert_eq!(("reg ".into(), 3), normal("reg read 0x10", "0wD"));
+        assert_eq!(("".into(), 0), normal("reg read 0x10", "dd"));
+        assert_eq!(("reg ad 0x10".into(), 4), normal("reg read 0x10", "0wdta"));
+    }
+
+    #[test]
+    fn change_word_keeps_space() {
+        assert_eq!(
+            ("reg write 0x10".into(), 8),
+            normal("reg read 0x10", "0wcwwrite\u{1b}")
+        );
+    }
+
+    #[test]
+    fn yank_and_put() {
+        assert_eq!(("reg reg read".into(), 3), normal("reg read", "0ywP"));
+        assert_eq!(("rreg eg read".into(), 4), normal("reg read", "0ywp"));
+    }
+
+    #[test]
+    fn delete_char_and_repeat() {
+        assert_eq!(("g read".into(), 0), normal("reg read", "0x."));
+        assert_eq!(("10".into(), 0), normal("reg read 0x10", "0dw.x."));
+    }
+
+    #[test]
+    fn repeat_insert() {
+        assert_eq!(("ab!!".into(), 3), normal("ab", "a!\u{1b}."));
+    }
+
+    #[test]
+    fn oversized_counts() {
+        let huge = "99999999999999999999";
+        assert_eq!(
+            ("reg read".into(), 7),
+            normal("reg read", &format!("0{}l", huge))
+        );
+        assert_eq!(
+            ("".into(), 0),
+            normal("reg read 0x10", &format!("0{}d{}w", huge, huge))
+        );
+        assert_eq!(("".into(), 0), normal("reg read", &format!("0{}x", huge)));
+        assert_eq!(
+            ("reg read".into(), 0),
+            normal("reg read", &format!("{}b", huge))
+        );
+
+        let (contents, _) = normal("ab", &format!("0yl{}p", huge));
+        assert_eq!(super::MAX_COUNT + 2, contents.len());
+    }
+}
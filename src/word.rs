@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+// What counts as part of a word for word-wise motion and deletion
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Words {
+    // Anything that isn't whitespace, e.g. `dev/0x1f:reg` is one word
+    #[default]
+    Whitespace,
+    // Alphanumerics and underscores, e.g. `dev/0x1f:reg` is three words
+    Punctuation,
+}
+
+impl Words {
+    fn is_word(&self, c: char) -> bool {
+        match self {
+            Self::Whitespace => !c.is_whitespace(),
+            Self::Punctuation => c.is_alphanumeric() || c == '_',
+        }
+    }
+
+    // Start of the word before cursor, skipping any separators first
+    pub fn prev(&self, line: &str, cursor: usize) -> usize {
+        let chars: Vec<char> = line.chars().take(cursor).collect();
+        let mut index = chars.len();
+
+        while index > 0 && !self.is_word(chars[index - 1]) {
+            index -= 1;
+        }
+        while index > 0 && self.is_word(chars[index - 1]) {
+            index -= 1;
+        }
+
+        index
+    }
+
+    // End of the word after cursor, skipping any separators first
+    pub fn next(&self, line: &str, cursor: usize) -> usize {
+        let chars: Vec<char> = line.chars().collect();
+        let mut index = cursor.min(chars.len());
+
+        while index < chars.len() && !self.is_word(chars[index]) {
+            index += 1;
+        }
+        while index < chars.len() && self.is_word(chars[index]) {
+            index += 1;
+        }
+
+        index
+    }
+}
+
+impl FromStr for Words {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "whitespace" => Ok(Self::Whitespace),
+            "punctuation" => Ok(Self::Punctuation),
+            _ => Err(Error::Value(format!(
+                "unknown word style {:?}, expected \"whitespace\" or \"punctuation\"",
+                value
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Words;
+
+    const LINE: &str = "read dev/0x1f:reg  now";
+
+    #[test]
+    fn whitespace_words() {
+        assert_eq!(5, Words::Whitespace.prev(LINE, 17));
+        assert_eq!(5, Words::Whitespace.prev(LINE, 19));
+        assert_eq!(17, Words::Whitespace.next(LINE, 5));
+        assert_eq!(22, Words::Whitespace.next(LINE, 17));
+    }
+
+    #[test]
+    fn punctuation_words() {
+        assert_eq!(14, Words::Punctuation.prev(LINE, 17));
+        assert_eq!(9, Words::Punctuation.prev(LINE, 14));
+        assert_eq!(8, Words::Punctuation.next(LINE, 5));
+        assert_eq!(13, Words::Punctuation.next(LINE, 8));
+    }
+
+    #[test]
+    fn clamps_at_ends() {
+        assert_eq!(0, Words::Punctuation.prev(LINE, 0));
+        assert_eq!(22, Words::Punctuation.next(LINE, 22));
+        assert_eq!(22, Words::Punctuation.next(LINE, 100));
+    }
+}